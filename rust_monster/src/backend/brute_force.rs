@@ -0,0 +1,258 @@
+use backend::MonsterSolver;
+use error::MonsterError;
+use problem::Problem;
+use simulation::{simulate_with_health, solution_is_valid_with_health};
+use solution::{solve_with, Objective, SolveOptions, SolveReport};
+
+use std::time::Instant;
+
+// Beyond these the number of boost multisets gets out of hand: with both at
+// the limit there are C(16, 8) = 12870 of them. The limit on boosts covers
+// damage and health boosts together, which with both kinds makes for at most
+// C(24, 8) = 735471 pairs of multisets.
+pub const BRUTE_FORCE_MAX_HEROES: usize = 8;
+pub const BRUTE_FORCE_MAX_BOOSTS: usize = 8;
+
+// Tries every multiset of boosts, fewest boosts first, by simulating the
+// combat. With health boosts, every pair of multisets of the two kinds is
+// tried, again by their total size. It is far too slow for real use, but it
+// shares nothing with the other backends, which makes it a good reference for
+// them. Every multiset tried counts as a node towards the node limit.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BruteForceBackend;
+
+impl MonsterSolver for BruteForceBackend {
+    fn name(&self) -> &'static str {
+        "brute_force"
+    }
+
+    fn solve(
+        &self,
+        problem: &Problem,
+        options: &SolveOptions,
+    ) -> Result<SolveReport, MonsterError> {
+        if problem.heroes.len() > BRUTE_FORCE_MAX_HEROES {
+            return Err(MonsterError::TooLarge(format!(
+                "brute force handles at most {} heroes, got {}",
+                BRUTE_FORCE_MAX_HEROES,
+                problem.heroes.len()
+            )));
+        }
+        let max_health_boosts = if problem.has_health_boosts() {
+            problem.rules.max_health_boosts
+        } else {
+            0
+        };
+        let all_boosts = problem.max_boosts.saturating_add(max_health_boosts);
+        if all_boosts > BRUTE_FORCE_MAX_BOOSTS {
+            return Err(MonsterError::TooLarge(format!(
+                "brute force handles at most {} boosts, got {}",
+                BRUTE_FORCE_MAX_BOOSTS, all_boosts
+            )));
+        }
+
+        // The best solution so far, with its score; lower is better.
+        let mut best: Option<(usize, usize, Vec<usize>, Vec<usize>)> = None;
+        let start = Instant::now();
+        let mut nodes = 0;
+        let mut stopped = None;
+        'search: for total in 0..=problem.max_boosts + max_health_boosts {
+            let health_sizes =
+                total.saturating_sub(problem.max_boosts)..=total.min(max_health_boosts);
+            'sizes: for health_size in health_sizes {
+                let mut health_boosts = vec![0; health_size];
+                loop {
+                    let mut boosts = vec![0; total - health_size];
+                    loop {
+                        stopped = options.limit_reached(start.elapsed(), nodes);
+                        if stopped.is_some() {
+                            break 'search;
+                        }
+                        nodes += 1;
+                        if within_caps(problem, &boosts, &health_boosts)
+                            && solution_is_valid_with_health(problem, &boosts, &health_boosts)?
+                        {
+                            let turn = simulate_with_health(problem, &boosts, &health_boosts)?.turn;
+                            let score = score(options.objective, turn, &boosts, &health_boosts);
+                            if best
+                                .as_ref()
+                                .is_none_or(|&(best_score, _, _, _)| score < best_score)
+                            {
+                                best = Some((score, turn, boosts.clone(), health_boosts.clone()));
+                            }
+                            if options.objective == Objective::AnyFeasible {
+                                break 'sizes;
+                            }
+                        }
+                        if !next_multiset(&mut boosts, problem.heroes.len()) {
+                            break;
+                        }
+                    }
+                    if !next_multiset(&mut health_boosts, problem.heroes.len()) {
+                        break;
+                    }
+                }
+            }
+            // Going by size, the first solution has the fewest boosts.
+            match options.objective {
+                Objective::AnyFeasible | Objective::FewestBoosts if best.is_some() => break,
+                _ => {}
+            }
+        }
+        let report = match best {
+            Some((_, turn, boosts, health_boosts)) => {
+                let report = SolveReport::solved(turn, boosts);
+                if problem.has_health_boosts() {
+                    report.with_health_boosts(health_boosts)
+                } else {
+                    report
+                }
+            }
+            None => SolveReport::infeasible(),
+        };
+        Ok(match stopped {
+            Some(status) => report.with_status(status),
+            None => report,
+        })
+    }
+}
+
+// The simulation rejects boosts beyond the caps as an error, which here just
+// means trying the next ones.
+fn within_caps(problem: &Problem, boosts: &[usize], health_boosts: &[usize]) -> bool {
+    (0..problem.heroes.len()).all(|hero| {
+        let count = boosts
+            .iter()
+            .chain(health_boosts)
+            .filter(|&&boost| boost == hero)
+            .count();
+        count <= problem.boost_cap(hero)
+    })
+}
+
+// Checks every boost multiset through the simulation, as a reference for the
+// real backends. Only small problems are accepted.
+pub fn solve_brute_force(
+    problem: &Problem,
+    options: &SolveOptions,
+) -> Result<SolveReport, MonsterError> {
+    solve_with(problem, &BruteForceBackend, options)
+}
+
+fn score(objective: Objective, turn: usize, boosts: &[usize], health_boosts: &[usize]) -> usize {
+    match objective {
+        Objective::AnyFeasible => 0,
+        Objective::FewestBoosts => boosts.len() + health_boosts.len(),
+        Objective::EarliestKill => turn,
+        Objective::FewestHeroes => {
+            let mut heroes: Vec<usize> = boosts.iter().chain(health_boosts).copied().collect();
+            heroes.sort_unstable();
+            heroes.dedup();
+            heroes.len()
+        }
+    }
+}
+
+// Steps to the next sorted list of hero indices below `heroes`, in
+// lexicographic order.
+pub(crate) fn next_multiset(boosts: &mut [usize], heroes: usize) -> bool {
+    for i in (0..boosts.len()).rev() {
+        if boosts[i] + 1 < heroes {
+            let hero = boosts[i] + 1;
+            for boost in &mut boosts[i..] {
+                *boost = hero;
+            }
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use problem::test_problem as problem;
+    use solution::SolveStatus;
+    use std::time::Duration;
+
+    #[test]
+    fn multisets_in_order() {
+        let mut boosts = vec![0, 0];
+        let mut seen = vec![boosts.clone()];
+        while next_multiset(&mut boosts, 3) {
+            seen.push(boosts.clone());
+        }
+        assert_eq!(
+            vec![
+                vec![0, 0],
+                vec![0, 1],
+                vec![0, 2],
+                vec![1, 1],
+                vec![1, 2],
+                vec![2, 2]
+            ],
+            seen
+        );
+        assert!(!next_multiset(&mut [], 3));
+    }
+
+    #[test]
+    fn brute_force_objectives() {
+        let solve = |objective| {
+            let options = SolveOptions {
+                objective,
+                ..SolveOptions::default()
+            };
+            let report = solve_brute_force(&problem(), &options).unwrap();
+            (report.turn.unwrap(), report.boosts.unwrap())
+        };
+        assert_eq!((2, vec![1]), solve(Objective::AnyFeasible));
+        assert_eq!((2, vec![1]), solve(Objective::FewestBoosts));
+        assert_eq!((1, vec![1, 1]), solve(Objective::EarliestKill));
+        assert_eq!((2, vec![1]), solve(Objective::FewestHeroes));
+    }
+
+    #[test]
+    fn brute_force_node_limit() {
+        // The empty multiset and the one boost for hero 0 don't work.
+        let options = |node_limit| SolveOptions {
+            objective: Objective::EarliestKill,
+            node_limit: Some(node_limit),
+            ..SolveOptions::default()
+        };
+        let report = solve_brute_force(&problem(), &options(2)).unwrap();
+        assert_eq!(SolveStatus::Unknown, report.status);
+        assert_eq!(None, report.boosts);
+        let report = solve_brute_force(&problem(), &options(3)).unwrap();
+        assert_eq!(SolveStatus::Unknown, report.status);
+        assert_eq!(Some(vec![1]), report.boosts);
+        let report = solve_brute_force(&problem(), &options(100)).unwrap();
+        assert_eq!(SolveStatus::Solved, report.status);
+        assert_eq!(Some(vec![1, 1]), report.boosts);
+        let timed = SolveOptions {
+            time_limit: Some(Duration::from_secs(0)),
+            ..SolveOptions::default()
+        };
+        let report = solve_brute_force(&problem(), &timed).unwrap();
+        assert_eq!(SolveStatus::TimedOut, report.status);
+        assert_eq!(None, report.boosts);
+    }
+
+    #[test]
+    fn brute_force_too_large() {
+        let mut problem = problem();
+        problem.max_boosts = BRUTE_FORCE_MAX_BOOSTS + 1;
+        match solve_brute_force(&problem, &SolveOptions::default()) {
+            Err(MonsterError::TooLarge(_)) => {}
+            other => panic!("Expected the problem to be too large, got {:?}", other),
+        }
+        // Damage and health boosts are tried in pairs, so they count together.
+        problem.max_boosts = BRUTE_FORCE_MAX_BOOSTS;
+        problem.rules.health_boost = 1;
+        problem.rules.max_health_boosts = 1;
+        match solve_brute_force(&problem, &SolveOptions::default()) {
+            Err(MonsterError::TooLarge(_)) => {}
+            other => panic!("Expected the problem to be too large, got {:?}", other),
+        }
+    }
+}
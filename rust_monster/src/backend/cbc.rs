@@ -0,0 +1,666 @@
+use backend::MonsterSolver;
+use error::{MonsterError, OrOverflow};
+use lp_modeler::dsl::*;
+use lp_modeler::format::lp_format::LpFileFormat;
+use lp_modeler::solvers::{CbcSolver, Status};
+use problem::Problem;
+use simulation::solution_is_valid;
+use solution::{
+    build_combat, to_i64, Combat, ModelSize, Objective, SolveOptions, SolveReport, SolveStatus,
+};
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::{self, Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How long past the time limit `cbc` gets to stop by itself before it is
+// killed; it only checks the clock every so often.
+const CBC_GRACE: Duration = Duration::from_secs(1);
+
+// Solves the problem as an integer linear program using the external `cbc`
+// binary.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CbcBackend;
+
+impl CbcBackend {
+    // Whether a `cbc` binary can be started at all.
+    pub fn is_available() -> bool {
+        Command::new("cbc")
+            .arg("-quit")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok()
+    }
+}
+
+struct DynVariable {
+    index: usize,
+    rep: LpInteger,
+    label: String,
+}
+
+#[allow(dead_code)]
+impl DynVariable {
+    pub fn new(name: &str, index: usize) -> DynVariable {
+        let label = format!("{}_{}", name, index);
+        DynVariable {
+            index,
+            rep: LpInteger::new(label.as_str()),
+            label,
+        }
+    }
+}
+
+impl MonsterSolver for CbcBackend {
+    fn name(&self) -> &'static str {
+        "cbc"
+    }
+
+    fn solve(
+        &self,
+        problem: &Problem,
+        options: &SolveOptions,
+    ) -> Result<SolveReport, MonsterError> {
+        problem.reject_health_boosts("cbc")?;
+        let model = match build_model(problem, options.objective)? {
+            Some(model) => model,
+            None => return Ok(SolveReport::infeasible()),
+        };
+        if let Some(ref path) = options.lp_file {
+            fs::write(path, model.lp.to_lp_file_format()).map_err(|e| {
+                MonsterError::Solver(format!("could not write {}: {}", path.display(), e))
+            })?;
+        }
+
+        let run = run_cbc(&model.lp, options)?;
+        Ok(read_report(problem, &model, &run)?.with_model_size(model.size()))
+    }
+}
+
+// The answer from a run of cbc, checked against the simulation.
+fn read_report(
+    problem: &Problem,
+    model: &LpModel,
+    run: &CbcRun,
+) -> Result<SolveReport, MonsterError> {
+    let works = |boosts: &[usize]| matches!(solution_is_valid(problem, boosts), Ok(true));
+    let found = model.read_solution(&run.results);
+    Ok(match run.status {
+        SolveStatus::Infeasible => SolveReport::infeasible(),
+        SolveStatus::Solved => match found {
+            Some((turn, boosts)) => {
+                if !works(&boosts) {
+                    return Err(MonsterError::Solver(format!(
+                        "cbc found boosts that don't work: {:?}",
+                        boosts
+                    )));
+                }
+                SolveReport::solved(turn, boosts)
+            }
+            None => SolveReport::infeasible(),
+        },
+        // Whatever cbc had found when it stopped need not work at all, as it
+        // may be the relaxation rather than an integer solution, and can even
+        // break the limits on boosts.
+        SolveStatus::TimedOut | SolveStatus::Unknown => {
+            let report = match found {
+                Some((turn, boosts)) if works(&boosts) => SolveReport::solved(turn, boosts),
+                _ => SolveReport::infeasible(),
+            };
+            let report = report.with_status(run.status);
+            if run.killed && report.boosts.is_none() {
+                report.with_note(
+                    "cbc was killed past the time limit, and no solution could be \
+                     recovered from it",
+                )
+            } else {
+                report
+            }
+        }
+    })
+}
+
+// How far a run of `cbc` got, and the values of the variables.
+struct CbcRun {
+    status: SolveStatus,
+    results: HashMap<String, f32>,
+    // Whether cbc was killed for overrunning the time limit, in which case
+    // it rarely got to write down what it had found.
+    killed: bool,
+}
+
+// Runs `cbc` on the model within the limits of the options.
+fn run_cbc(lp: &LpProblem, options: &SolveOptions) -> Result<CbcRun, MonsterError> {
+    // Runs on several threads at once must not share files.
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+    let run = RUNS.fetch_add(1, Ordering::Relaxed);
+    let base = format!("rust_monster-{}-{}", process::id(), run);
+    let lp_path = env::temp_dir().join(format!("{}.lp", base));
+    let solution_path = env::temp_dir().join(format!("{}.sol", base));
+    let result = run_cbc_files(lp, options, &lp_path, &solution_path);
+    let _ = fs::remove_file(&lp_path);
+    let _ = fs::remove_file(&solution_path);
+    result
+}
+
+fn run_cbc_files(
+    lp: &LpProblem,
+    options: &SolveOptions,
+    lp_path: &Path,
+    solution_path: &Path,
+) -> Result<CbcRun, MonsterError> {
+    fs::write(lp_path, lp.to_lp_file_format())
+        .map_err(|e| io_error(&format!("could not write {}", lp_path.display()), e))?;
+
+    let mut command = Command::new("cbc");
+    command.arg(lp_path);
+    if let Some(limit) = options.time_limit {
+        command.arg("sec").arg(limit.as_secs_f64().to_string());
+    }
+    if let Some(limit) = options.node_limit {
+        command.arg("maxN").arg(limit.to_string());
+    }
+    command
+        .arg("solve")
+        .arg("solution")
+        .arg(solution_path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    let mut child = command
+        .spawn()
+        .map_err(|e| io_error("could not run cbc", e))?;
+
+    let deadline = options
+        .time_limit
+        .map(|limit| Instant::now() + limit + CBC_GRACE);
+    match wait_until(&mut child, deadline)? {
+        Some(exit) if !exit.success() => Err(MonsterError::Solver(format!("cbc failed: {}", exit))),
+        Some(_) => read_cbc_solution(solution_path, false),
+        None => read_cbc_solution(solution_path, true),
+    }
+}
+
+fn io_error(what: &str, e: io::Error) -> MonsterError {
+    MonsterError::Solver(format!("{}: {}", what, e))
+}
+
+// Waits for the child to exit, or kills it once past the deadline, in which
+// case there is no exit status.
+fn wait_until(
+    child: &mut Child,
+    deadline: Option<Instant>,
+) -> Result<Option<ExitStatus>, MonsterError> {
+    loop {
+        match child.try_wait().map_err(|e| io_error("cbc", e))? {
+            Some(exit) => return Ok(Some(exit)),
+            None if deadline.is_some_and(|deadline| Instant::now() >= deadline) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Ok(None);
+            }
+            None => thread::sleep(Duration::from_millis(10)),
+        }
+    }
+}
+
+// Reads the solution file `cbc` wrote. A killed cbc has timed out whatever
+// the file says, and whatever it left, if anything, is only used if it can be
+// read in full.
+fn read_cbc_solution(path: &Path, killed: bool) -> Result<CbcRun, MonsterError> {
+    // Both "Stopped on time" and "Stopped on nodes" read as suboptimal.
+    let timed_out = killed
+        || fs::read_to_string(path)
+            .map(|solution| solution.starts_with("Stopped on time"))
+            .unwrap_or(false);
+    let solution = CbcSolver::new()
+        .temp_solution_file(path.to_string_lossy().into_owned())
+        .read_solution();
+    let (status, results) = match solution {
+        Ok(solution) => solution,
+        Err(_) if killed => (Status::NotSolved, HashMap::new()),
+        Err(e) => return Err(MonsterError::Solver(e)),
+    };
+    let status = match status {
+        _ if killed => SolveStatus::TimedOut,
+        Status::Optimal => SolveStatus::Solved,
+        Status::Infeasible => SolveStatus::Infeasible,
+        _ if timed_out => SolveStatus::TimedOut,
+        _ => SolveStatus::Unknown,
+    };
+    Ok(CbcRun {
+        status,
+        results,
+        killed,
+    })
+}
+
+// The integer program for a problem, along with the variables needed to read
+// back the answer.
+struct LpModel {
+    lp: LpProblem,
+    boosts: Vec<DynVariable>,
+    // The turn each stage variable stands for.
+    stages: Vec<(usize, String)>,
+    // Variables that only serve the objective.
+    helpers: usize,
+}
+
+impl LpModel {
+    fn size(&self) -> ModelSize {
+        ModelSize {
+            variables: self.boosts.len() + self.stages.len() + self.helpers,
+            constraints: self.lp.constraints.len(),
+        }
+    }
+
+    fn read_solution(&self, results: &HashMap<String, f32>) -> Option<(usize, Vec<usize>)> {
+        // The values are integers up to the solver's tolerance, either way.
+        let value = |label: &String| results.get(label).unwrap_or(&0.0).round();
+        let &(turn, _) = self.stages.iter().find(|&(_, label)| value(label) != 0.0)?;
+        let mut result = Vec::new();
+        for boost in &self.boosts {
+            for _ in 0..(value(&boost.label) as usize) {
+                result.push(boost.index)
+            }
+        }
+        Some((turn, result))
+    }
+}
+
+// Returns None when at no stage can the monster be slain.
+fn build_model(problem: &Problem, objective: Objective) -> Result<Option<LpModel>, MonsterError> {
+    let combat = build_combat(problem)?;
+    let boosts: Vec<DynVariable> = combat
+        .boostable
+        .iter()
+        .map(|&(hero, _)| DynVariable::new("boost", hero))
+        .collect();
+    let mut stages: Vec<(usize, String)> = Vec::new();
+    let mut stage_vars: Vec<LpBinary> = Vec::new();
+    let mut lp = LpProblem::new("Monster", LpObjective::Maximize);
+
+    {
+        let max_boost_damage = to_i64(problem.max_boosts, "the number of boosts")?
+            .checked_mul(problem.boost_damage)
+            .or_overflow("the damage of all boosts together")?;
+
+        let mut remaining_health = problem.monster_health;
+        let mut active_phase = 0;
+
+        set_max_boosts(&mut lp, &boosts, problem.max_boosts)?;
+        for boost in &boosts {
+            let cap = combat.caps[boost.index];
+            if cap < problem.max_boosts {
+                let cap = lp_number(to_i64(cap, "a boost cap")?, "a boost cap")?;
+                lp += (1 * &boost.rep).le(cap);
+            }
+        }
+
+        for turn in 0..combat.phases.last().unwrap().end_stage {
+            // A hero who dies on the first turn leaves an empty phase.
+            while turn == combat.phases[active_phase].end_stage {
+                active_phase += 1;
+            }
+            // The monster is still alive, so neither this nor the subtraction
+            // of the later damage can overflow.
+            remaining_health -= combat.phases[active_phase].early_damage;
+            // The heroes attacking first killed the monster unboosted, and
+            // boosts would only make that happen sooner. Carrying on would also
+            // break the big-M constants below, which assume it's alive.
+            if remaining_health <= 0 {
+                break;
+            }
+
+            let max_total_damage = to_i64(turn + 1, "the turn number")?
+                .checked_mul(max_boost_damage)
+                .and_then(|damage| damage.checked_add(combat.chosen_damage))
+                .or_overflow("the most damage the chosen hero can deal")?;
+            if max_total_damage >= remaining_health {
+                let label = format!("stage_{}", turn);
+                stage_vars.push(LpBinary::new(label.as_str()));
+                stages.push((turn, label));
+                let stage = &stage_vars.last().unwrap();
+
+                // The chosen hero's boosts count once more after their attack.
+                let mut pre_chosen_constraints =
+                    get_boost_contributions(&combat, &boosts, problem.boost_damage, turn, turn)?;
+                let mut post_chosen_constraints = get_boost_contributions(
+                    &combat,
+                    &boosts,
+                    problem.boost_damage,
+                    turn,
+                    turn + 1,
+                )?;
+
+                // If the stage has been chosen, we need an upper bound on the damage done
+                // before the chosen hero and a lower bound on the damage done by the chosen
+                // hero themselves.
+                // We add large constants to ensure that when the stage is not selected,
+                // these constraints have no effect.
+                let big_m = lp_number(max_total_damage, "a big-M constant")?;
+                pre_chosen_constraints.push(big_m * *stage);
+                post_chosen_constraints.push(-big_m * *stage);
+
+                // -1 to be exclusive, we don't want the monster to die yet.
+                let pre_limit = remaining_health
+                    .checked_add(max_total_damage - 1)
+                    .or_overflow("the damage limit before the chosen hero")?;
+                let post_need = remaining_health - combat.chosen_damage - max_total_damage;
+                lp += lp_sum(&pre_chosen_constraints).le(lp_number(pre_limit, "a damage limit")?);
+                lp += lp_sum(&post_chosen_constraints).ge(lp_number(post_need, "a damage limit")?);
+            }
+
+            remaining_health -= combat
+                .chosen_damage
+                .checked_add(combat.phases[active_phase].late_damage)
+                .or_overflow("the damage dealt in a turn")?;
+
+            if remaining_health <= 0 {
+                break;
+            }
+        }
+    }
+
+    // At no stage can the monster be slain.
+    if stages.is_empty() {
+        return Ok(None);
+    }
+
+    let mut helpers = 0;
+    if objective == Objective::AnyFeasible {
+        lp += lp_sum(&stage_vars);
+    } else {
+        // At most one stage can hold anyway, and insisting on one keeps the
+        // minimum from being "no kill at all".
+        lp.objective_type = LpObjective::Minimize;
+        lp += lp_sum(&stage_vars).equal(1);
+        match objective {
+            Objective::FewestBoosts => {
+                lp += lp_sum(&boost_expressions(&boosts));
+            }
+            Objective::EarliestKill => {
+                let mut turns: Vec<LpExpression> = Vec::new();
+                for (&(turn, _), stage) in stages.iter().zip(&stage_vars) {
+                    turns.push(
+                        lp_number(to_i64(turn, "the turn number")?, "a turn number")? * stage,
+                    );
+                }
+                lp += lp_sum(&turns);
+            }
+            Objective::FewestHeroes => {
+                // A hero only gets boosts if they're marked as used.
+                let used: Vec<LpBinary> = boosts
+                    .iter()
+                    .map(|boost| LpBinary::new(&format!("used_{}", boost.index)))
+                    .collect();
+                let max_boosts = lp_number(
+                    to_i64(problem.max_boosts, "the number of boosts")?,
+                    "the number of boosts",
+                )?;
+                for (boost, used) in boosts.iter().zip(&used) {
+                    lp += (&boost.rep - max_boosts * used).le(0);
+                }
+                helpers = used.len();
+                lp += lp_sum(&used);
+            }
+            Objective::AnyFeasible => unreachable!(),
+        }
+    }
+    Ok(Some(LpModel {
+        lp,
+        boosts,
+        stages,
+        helpers,
+    }))
+}
+
+fn get_boost_contributions(
+    combat: &Combat,
+    boosts: &[DynVariable],
+    boost_damage: i64,
+    turn: usize,
+    chosen_attacks: usize,
+) -> Result<Vec<LpExpression>, MonsterError> {
+    combat
+        .boostable
+        .iter()
+        .zip(boosts.iter())
+        .map(|(&(hero, turns), boost)| {
+            let attacks = if hero == combat.chosen_hero {
+                chosen_attacks
+            } else {
+                combat.attacks_before_chosen(hero, turns, turn)
+            };
+            let damage = to_i64(attacks, "the number of attacks")?
+                .checked_mul(boost_damage)
+                .or_overflow("the damage of a boost")?;
+            Ok(lp_number(damage, "a boost coefficient")? * &boost.rep)
+        })
+        .collect()
+}
+
+#[allow(clippy::identity_op)]
+fn boost_expressions(boosts: &[DynVariable]) -> Vec<LpExpression> {
+    // Yes, the 1* looks weird, but I can't find a different way to convert an LpInteger
+    // to an LpExpression without borrowing it.
+    boosts.iter().map(|boost| 1 * &boost.rep).collect()
+}
+
+fn set_max_boosts(
+    lp: &mut LpProblem,
+    boosts: &[DynVariable],
+    max_boosts: usize,
+) -> Result<(), MonsterError> {
+    let max_boosts = lp_number(
+        to_i64(max_boosts, "the number of boosts")?,
+        "the number of boosts",
+    )?;
+    *lp += lp_sum(&boost_expressions(boosts)).le(max_boosts);
+    Ok(())
+}
+
+// lp-modeler keeps every number in the model as an f32, which only holds
+// integers up to 2^24 exactly. Anything bigger would quietly round, and with
+// it the answer, so we refuse instead.
+const LP_MAX_EXACT: i64 = 1 << 24;
+
+fn lp_number(value: i64, what: &str) -> Result<i32, MonsterError> {
+    if value.abs() > LP_MAX_EXACT {
+        return Err(MonsterError::Overflow(format!(
+            "{} of the LP model: {} is beyond the {} its numbers hold exactly",
+            what, value, LP_MAX_EXACT
+        )));
+    }
+    Ok(value as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use problem::{Hero, Rules};
+    use std::path::PathBuf;
+
+    fn barely_enough() -> Problem {
+        Problem {
+            monster_health: 10,
+            heroes: vec![Hero::new(2, 4)],
+            chosen_hero: 0,
+            boost_damage: 1,
+            max_boosts: 1,
+            rules: Rules::default(),
+        }
+    }
+
+    #[test]
+    fn model_size() {
+        // One boost variable and a stage for the second turn, with the
+        // boost limit and two constraints for the stage.
+        let model = build_model(&barely_enough(), Objective::AnyFeasible)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            ModelSize {
+                variables: 2,
+                constraints: 3,
+            },
+            model.size()
+        );
+        assert_eq!(vec![(1, "stage_1".to_string())], model.stages);
+    }
+
+    #[test]
+    fn read_chosen_stage() {
+        let model = build_model(&barely_enough(), Objective::AnyFeasible)
+            .unwrap()
+            .unwrap();
+        let mut results = HashMap::new();
+        assert_eq!(None, model.read_solution(&results));
+        results.insert("stage_1".to_string(), 1.0);
+        results.insert("boost_0".to_string(), 1.0);
+        assert_eq!(Some((1, vec![0])), model.read_solution(&results));
+        // Values are integers only up to the solver's tolerance.
+        results.insert("stage_1".to_string(), 0.99999);
+        results.insert("boost_0".to_string(), 0.99999);
+        assert_eq!(Some((1, vec![0])), model.read_solution(&results));
+    }
+
+    #[test]
+    fn stopped_run_with_broken_boosts() {
+        // Two boosts where one is allowed.
+        let problem = barely_enough();
+        let model = build_model(&problem, Objective::AnyFeasible)
+            .unwrap()
+            .unwrap();
+        let run = |status, killed| CbcRun {
+            status,
+            results: [("stage_1".to_string(), 1.0), ("boost_0".to_string(), 2.0)]
+                .iter()
+                .cloned()
+                .collect(),
+            killed,
+        };
+        let report = read_report(&problem, &model, &run(SolveStatus::Unknown, false)).unwrap();
+        assert_eq!((SolveStatus::Unknown, None), (report.status, report.boosts));
+        assert_eq!(None, report.note);
+        let report = read_report(&problem, &model, &run(SolveStatus::TimedOut, true)).unwrap();
+        assert_eq!(
+            (SolveStatus::TimedOut, None),
+            (report.status, report.boosts)
+        );
+        assert!(report.note.is_some());
+        match read_report(&problem, &model, &run(SolveStatus::Solved, false)) {
+            Err(MonsterError::Solver(_)) => {}
+            other => panic!("Expected a solver error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn no_stages() {
+        let mut problem = barely_enough();
+        problem.monster_health = 100;
+        assert!(build_model(&problem, Objective::AnyFeasible)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn rejects_inexact_numbers() {
+        let mut problem = barely_enough();
+        problem.monster_health = 1 << 25;
+        problem.heroes[0].damage = 1 << 24;
+        match build_model(&problem, Objective::AnyFeasible) {
+            Err(MonsterError::Overflow(_)) => {}
+            Ok(_) => panic!("Expected the model to be rejected"),
+            Err(e) => panic!("Expected an overflow, got {}", e),
+        }
+    }
+
+    #[test]
+    fn no_stage_after_early_kill() {
+        // The front hero kills the monster before the chosen hero attacks on
+        // the third turn, so there must be no stage for it.
+        let problem = Problem {
+            monster_health: 44,
+            heroes: vec![Hero::new(3, 15), Hero::new(2, 6)],
+            chosen_hero: 1,
+            boost_damage: 2,
+            max_boosts: 1,
+            rules: Rules::default(),
+        };
+        let model = build_model(&problem, Objective::AnyFeasible)
+            .unwrap()
+            .unwrap();
+        assert_eq!(vec![(1, "stage_1".to_string())], model.stages);
+    }
+
+    fn solution_file(name: &str, contents: Option<&str>) -> PathBuf {
+        let path = env::temp_dir().join(format!("rust_monster-test-{}-{}", process::id(), name));
+        if let Some(contents) = contents {
+            fs::write(&path, contents).unwrap();
+        }
+        path
+    }
+
+    #[test]
+    fn overrunning_child_is_killed() {
+        let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+        let start = Instant::now();
+        assert_eq!(None, wait_until(&mut child, Some(start)).unwrap());
+        assert!(start.elapsed() < Duration::from_secs(5));
+        let mut child = Command::new("true").spawn().unwrap();
+        assert!(wait_until(&mut child, None).unwrap().unwrap().success());
+    }
+
+    #[test]
+    fn solution_statuses() {
+        // Reading a solution removes the file, but not when it fails.
+        let read = |name, contents, killed| {
+            let path = solution_file(name, contents);
+            let run = read_cbc_solution(&path, killed);
+            let _ = fs::remove_file(&path);
+            let run = run.unwrap();
+            (run.status, run.results.get("boost_0").copied())
+        };
+        let found = "      0 boost_0               2 0\n";
+        let optimal = format!("Optimal - objective value 1\n{}", found);
+        let stopped = format!("Stopped on time - objective value 1\n{}", found);
+        assert_eq!(
+            (SolveStatus::Solved, Some(2.0)),
+            read("optimal", Some(&optimal), false)
+        );
+        assert_eq!(
+            (SolveStatus::TimedOut, Some(2.0)),
+            read("time", Some(&stopped), false)
+        );
+        assert_eq!(
+            (SolveStatus::Unknown, None),
+            read(
+                "nodes",
+                Some("Stopped on iterations - objective value 0\n"),
+                false
+            )
+        );
+        // A killed cbc has timed out, whatever it left behind.
+        assert_eq!(
+            (SolveStatus::TimedOut, Some(2.0)),
+            read("killed", Some(&optimal), true)
+        );
+        assert_eq!((SolveStatus::TimedOut, None), read("nothing", None, true));
+        assert_eq!(
+            (SolveStatus::TimedOut, None),
+            read(
+                "partial",
+                Some("Optimal - objective value 1\n      0 boost_0"),
+                true
+            )
+        );
+        assert!(read_cbc_solution(&solution_file("missing", None), false).is_err());
+    }
+}
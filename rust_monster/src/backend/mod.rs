@@ -0,0 +1,97 @@
+use error::MonsterError;
+use problem::Problem;
+use solution::{SolveOptions, SolveReport};
+
+use std::fmt;
+use std::str::FromStr;
+
+mod brute_force;
+mod cbc;
+mod native;
+
+pub(crate) use self::brute_force::next_multiset;
+pub use self::brute_force::{
+    solve_brute_force, BruteForceBackend, BRUTE_FORCE_MAX_BOOSTS, BRUTE_FORCE_MAX_HEROES,
+};
+pub use self::cbc::CbcBackend;
+pub use self::native::NativeBackend;
+pub(crate) use self::native::{kill_options, solve_combat};
+
+// Anything that can find a boost vector letting the chosen hero land the
+// killing blow. Problems are validated before they are passed in.
+pub trait MonsterSolver {
+    fn name(&self) -> &'static str;
+    fn solve(&self, problem: &Problem, options: &SolveOptions)
+        -> Result<SolveReport, MonsterError>;
+}
+
+// The backends shipped with the crate, so that callers (and the command line)
+// can pick one by name.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Backend {
+    // Only for small problems, to check the others against.
+    BruteForce,
+    Cbc,
+    // The native backend needs no external process, so it works everywhere.
+    #[default]
+    Native,
+}
+
+impl Backend {
+    pub fn all() -> &'static [Backend] {
+        &[Backend::BruteForce, Backend::Cbc, Backend::Native]
+    }
+}
+
+impl MonsterSolver for Backend {
+    fn name(&self) -> &'static str {
+        match *self {
+            Backend::BruteForce => BruteForceBackend.name(),
+            Backend::Cbc => CbcBackend.name(),
+            Backend::Native => NativeBackend.name(),
+        }
+    }
+
+    fn solve(
+        &self,
+        problem: &Problem,
+        options: &SolveOptions,
+    ) -> Result<SolveReport, MonsterError> {
+        match *self {
+            Backend::BruteForce => BruteForceBackend.solve(problem, options),
+            Backend::Cbc => CbcBackend.solve(problem, options),
+            Backend::Native => NativeBackend.solve(problem, options),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Backend, String> {
+        Backend::all()
+            .iter()
+            .find(|backend| backend.name() == s)
+            .cloned()
+            .ok_or_else(|| format!("unknown backend: {}", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_backend_names() {
+        for backend in Backend::all() {
+            assert_eq!(Ok(*backend), backend.to_string().parse());
+        }
+        assert!("gurobi".parse::<Backend>().is_err());
+    }
+}
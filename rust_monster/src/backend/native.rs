@@ -0,0 +1,719 @@
+use backend::brute_force::next_multiset;
+use backend::MonsterSolver;
+use error::MonsterError;
+use problem::Problem;
+use solution::{
+    build_combat, ceil_div, combat_with_health, Combat, Objective, SolveOptions, SolveReport,
+    SolveStatus, Stage,
+};
+
+use std::cmp;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::time::Instant;
+
+// Every way of handing out the health boosts is tried in turn, so there had
+// better not be too many of them.
+const MAX_HEALTH_ALLOCATIONS: u128 = 10000;
+
+// Exact solver that needs no external process. It works directly on the
+// phases of the combat: within a phase the damage per turn is constant, so the
+// turns on which the chosen hero could possibly land the killing blow can be
+// found without stepping through the rest, and for each of those we search for
+// a boost allocation that makes it happen. Every such search counts as a node
+// towards the node limit.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NativeBackend;
+
+impl MonsterSolver for NativeBackend {
+    fn name(&self) -> &'static str {
+        "native"
+    }
+
+    fn solve(
+        &self,
+        problem: &Problem,
+        options: &SolveOptions,
+    ) -> Result<SolveReport, MonsterError> {
+        let mut limits = Limits::new(options);
+        let report = if problem.has_health_boosts() {
+            match solve_with_health(problem, options.objective, &mut limits)? {
+                Some((turn, boosts, health_boosts)) => {
+                    SolveReport::solved(turn, boosts).with_health_boosts(health_boosts)
+                }
+                None => SolveReport::infeasible(),
+            }
+        } else {
+            let combat = build_combat(problem)?;
+            match solve_counts(problem, &combat, options.objective, &[], &mut limits)? {
+                Some((turn, counts)) => SolveReport::solved(turn, expand_counts(&counts)),
+                None => SolveReport::infeasible(),
+            }
+        };
+        Ok(match limits.stopped {
+            Some(status) => report.with_status(status),
+            None => report,
+        })
+    }
+}
+
+// Keeps count of the nodes searched, and notes which limit of the options
+// stopped the search, if any. Once it has, every search comes up empty and
+// the solvers return the best they found before.
+struct Limits<'a> {
+    options: &'a SolveOptions,
+    start: Instant,
+    nodes: u64,
+    stopped: Option<SolveStatus>,
+}
+
+impl<'a> Limits<'a> {
+    fn new(options: &'a SolveOptions) -> Limits<'a> {
+        Limits {
+            options,
+            start: Instant::now(),
+            nodes: 0,
+            stopped: None,
+        }
+    }
+
+    // Whether there is room for one more node, which is then counted.
+    fn next_node(&mut self) -> bool {
+        if self.stopped.is_none() {
+            self.stopped = self.options.limit_reached(self.start.elapsed(), self.nodes);
+        }
+        self.nodes += 1;
+        self.stopped.is_none()
+    }
+}
+
+// Solves for a combat that has already been built, giving the turn of the
+// killing blow and the boosted heroes, one entry per boost.
+pub(crate) fn solve_combat(
+    problem: &Problem,
+    combat: &Combat,
+    objective: Objective,
+) -> Result<Option<(usize, Vec<usize>)>, MonsterError> {
+    let options = SolveOptions::default();
+    let found = solve_counts(problem, combat, objective, &[], &mut Limits::new(&options))?;
+    Ok(found.map(|(turn, counts)| (turn, expand_counts(&counts))))
+}
+
+// Like `solve_combat`, but giving the number of boosts per hero. Heroes in
+// `free` don't count against the fewest heroes, as they are boosted anyway.
+fn solve_counts(
+    problem: &Problem,
+    combat: &Combat,
+    objective: Objective,
+    free: &[usize],
+    limits: &mut Limits,
+) -> Result<Option<(usize, Vec<usize>)>, MonsterError> {
+    match objective {
+        // Turns are tried in order, so the first solution is the earliest.
+        Objective::AnyFeasible | Objective::EarliestKill => earliest_kill(
+            problem,
+            combat,
+            &combat.boostable,
+            problem.max_boosts,
+            limits,
+        ),
+        Objective::FewestBoosts => fewest_boosts(problem, combat, limits),
+        Objective::FewestHeroes => fewest_heroes(problem, combat, free, limits),
+    }
+}
+
+// The turn of the killing blow, the damage boosts and the health boosts.
+type HealthSolution = (usize, Vec<usize>, Vec<usize>);
+
+// Tries every way of handing out the health boosts, fewest first, and solves
+// for the damage boosts with each. Health boosts only change who dies when,
+// so of the ways that leave the same combat only the first is solved for,
+// unless they differ in who gets boosts and that is what counts.
+fn solve_with_health(
+    problem: &Problem,
+    objective: Objective,
+    limits: &mut Limits,
+) -> Result<Option<HealthSolution>, MonsterError> {
+    // The best solution so far, with its score; lower is better.
+    let mut best: Option<(usize, usize, Vec<usize>, Vec<usize>)> = None;
+    let mut seen = HashSet::new();
+    for health in health_allocations(problem)? {
+        let size: usize = health.iter().sum();
+        let used: Vec<usize> = (0..health.len()).filter(|&hero| health[hero] > 0).collect();
+        // Neither objective can do better than the health boosts do by
+        // themselves.
+        let floor = match objective {
+            Objective::FewestBoosts => Some(size),
+            Objective::FewestHeroes => Some(used.len()),
+            Objective::AnyFeasible | Objective::EarliestKill => None,
+        };
+        if let (Some(floor), Some(&(score, _, _, _))) = (floor, best.as_ref()) {
+            if score <= floor {
+                continue;
+            }
+        }
+
+        let combat = combat_with_health(problem, problem.chosen_hero, &health)?;
+        let key = (
+            combat.phases.clone(),
+            combat.boostable.clone(),
+            combat.caps.clone(),
+            if objective == Objective::FewestHeroes {
+                used.clone()
+            } else {
+                Vec::new()
+            },
+        );
+        if !seen.insert(key) {
+            continue;
+        }
+        let (turn, counts) = match solve_counts(problem, &combat, objective, &used, limits)? {
+            Some(found) => found,
+            None if limits.stopped.is_some() => break,
+            None => continue,
+        };
+        let score = match objective {
+            Objective::AnyFeasible => 0,
+            Objective::EarliestKill => turn,
+            Objective::FewestBoosts => size + counts.iter().sum::<usize>(),
+            Objective::FewestHeroes => (0..health.len())
+                .filter(|&hero| health[hero] > 0 || counts.get(hero).is_some_and(|&n| n > 0))
+                .count(),
+        };
+        if best
+            .as_ref()
+            .is_none_or(|&(best_score, _, _, _)| score < best_score)
+        {
+            best = Some((score, turn, counts, health));
+        }
+        if objective == Objective::AnyFeasible || limits.stopped.is_some() {
+            break;
+        }
+    }
+    Ok(
+        best.map(|(_, turn, counts, health)| {
+            (turn, expand_counts(&counts), expand_counts(&health))
+        }),
+    )
+}
+
+// The ways of handing out at most `max_health_boosts` health boosts within
+// the caps, as the number per hero, fewest boosts first.
+fn health_allocations(problem: &Problem) -> Result<Vec<Vec<usize>>, MonsterError> {
+    let hero_count = problem.heroes.len();
+    let max_health_boosts = problem.rules.max_health_boosts;
+    // There are C(hero_count + max_health_boosts, max_health_boosts) of them
+    // before the caps, and each step of this is a binomial coefficient too.
+    let mut total: u128 = 1;
+    for i in 1..=max_health_boosts as u128 {
+        total = total * (hero_count as u128 + i) / i;
+        if total > MAX_HEALTH_ALLOCATIONS {
+            return Err(MonsterError::TooLarge(format!(
+                "{} health boosts for {} heroes can be handed out in more than {} ways",
+                max_health_boosts, hero_count, MAX_HEALTH_ALLOCATIONS
+            )));
+        }
+    }
+
+    let mut allocations = Vec::new();
+    for size in 0..=max_health_boosts {
+        let mut boosts = vec![0; size];
+        loop {
+            let mut health = vec![0; hero_count];
+            for &hero in &boosts {
+                health[hero] += 1;
+            }
+            if (0..hero_count).all(|hero| health[hero] <= problem.boost_cap(hero)) {
+                allocations.push(health);
+            }
+            if !next_multiset(&mut boosts, hero_count) {
+                break;
+            }
+        }
+    }
+    Ok(allocations)
+}
+
+// Finds the first turn on which the chosen hero can land the killing blow
+// with at most `max_boosts` boosts, all on the given heroes, and the number
+// of boosts each hero needs for it.
+fn earliest_kill(
+    problem: &Problem,
+    combat: &Combat,
+    heroes: &[(usize, usize)],
+    max_boosts: usize,
+    limits: &mut Limits,
+) -> Result<Option<(usize, Vec<usize>)>, MonsterError> {
+    for stage in combat.stages(problem, max_boosts)? {
+        if !limits.next_node() {
+            break;
+        }
+        if let Some(counts) = search(&stage, combat, heroes, problem.boost_damage, max_boosts) {
+            return Ok(Some((stage.turn, counts)));
+        }
+    }
+    Ok(None)
+}
+
+// Every turn on which the chosen hero can land the killing blow with at most
+// `max_boosts` boosts, in order, each with the fewest boosts that make it
+// happen then. There can be a great many turns, so past `limit` of them we
+// give up.
+pub(crate) fn kill_options(
+    problem: &Problem,
+    combat: &Combat,
+    limit: usize,
+) -> Result<Vec<(usize, Vec<usize>)>, MonsterError> {
+    let search_with = |stage: &Stage, max_boosts: usize| {
+        search(
+            stage,
+            combat,
+            &combat.boostable,
+            problem.boost_damage,
+            max_boosts,
+        )
+    };
+    let mut options = Vec::new();
+    for (i, stage) in combat.stages(problem, problem.max_boosts)?.enumerate() {
+        if i == limit {
+            return Err(MonsterError::TooLarge(format!(
+                "more than {} turns on which hero {} could land the killing blow",
+                limit, combat.chosen_hero
+            )));
+        }
+        let mut best = match search_with(&stage, problem.max_boosts) {
+            Some(counts) => counts,
+            None => continue,
+        };
+        let mut lo = 0;
+        let mut hi: usize = best.iter().sum();
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match search_with(&stage, mid) {
+                Some(counts) => {
+                    hi = counts.iter().sum();
+                    best = counts;
+                }
+                None => lo = mid + 1,
+            }
+        }
+        options.push((stage.turn, expand_counts(&best)));
+    }
+    Ok(options)
+}
+
+// Anything that works with some number of boosts still works when more are
+// allowed, so we can bisect on the limit.
+fn fewest_boosts(
+    problem: &Problem,
+    combat: &Combat,
+    limits: &mut Limits,
+) -> Result<Option<(usize, Vec<usize>)>, MonsterError> {
+    let boostable = &combat.boostable;
+    let mut best = match earliest_kill(problem, combat, boostable, problem.max_boosts, limits)? {
+        Some(found) => found,
+        None => return Ok(None),
+    };
+    let mut lo = 0;
+    let mut hi: usize = best.1.iter().sum();
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        match earliest_kill(problem, combat, boostable, mid, limits)? {
+            Some(found) => {
+                hi = found.1.iter().sum();
+                best = found;
+            }
+            // Stopped early, `mid` may well have worked.
+            None if limits.stopped.is_some() => break,
+            None => lo = mid + 1,
+        }
+    }
+    Ok(Some(best))
+}
+
+// Tries every set of heroes, smallest first, on top of the free ones. That is
+// exponential in the number of boostable heroes, but heroes who attack
+// equally often and take as many boosts are interchangeable, and the answer
+// is rarely more than two or three.
+fn fewest_heroes(
+    problem: &Problem,
+    combat: &Combat,
+    free: &[usize],
+    limits: &mut Limits,
+) -> Result<Option<(usize, Vec<usize>)>, MonsterError> {
+    let mut distinct: Vec<(usize, usize)> = Vec::new();
+    for &(hero, turns) in &combat.boostable {
+        let side = |hero: usize| combat.ranks[hero].cmp(&combat.ranks[combat.chosen_hero]);
+        if !free.contains(&hero)
+            && !distinct.iter().any(|&(other, other_turns)| {
+                other_turns == turns
+                    && side(other) == side(hero)
+                    && combat.caps[other] == combat.caps[hero]
+            })
+        {
+            distinct.push((hero, turns));
+        }
+    }
+
+    for size in 0..=distinct.len() {
+        let mut indices: Vec<usize> = (0..size).collect();
+        loop {
+            let mut heroes: Vec<(usize, usize)> = combat
+                .boostable
+                .iter()
+                .copied()
+                .filter(|&(hero, _)| free.contains(&hero))
+                .chain(indices.iter().map(|&i| distinct[i]))
+                .collect();
+            heroes.sort_unstable();
+            if let Some(found) =
+                earliest_kill(problem, combat, &heroes, problem.max_boosts, limits)?
+            {
+                return Ok(Some(found));
+            }
+            if limits.stopped.is_some() {
+                return Ok(None);
+            }
+            if !next_combination(&mut indices, distinct.len()) {
+                break;
+            }
+        }
+    }
+    Ok(None)
+}
+
+// Steps to the next sorted selection of indices below `n`, in lexicographic
+// order.
+fn next_combination(indices: &mut [usize], n: usize) -> bool {
+    let size = indices.len();
+    for i in (0..size).rev() {
+        if indices[i] < n - size + i {
+            indices[i] += 1;
+            for j in i + 1..size {
+                indices[j] = indices[j - 1] + 1;
+            }
+            return true;
+        }
+    }
+    false
+}
+
+// Returns the number of boosts per hero that let the chosen hero strike on
+// the stage's turn, up to the chosen one or the last of the given heroes, who
+// are the only ones boosted.
+fn search(
+    stage: &Stage,
+    combat: &Combat,
+    heroes: &[(usize, usize)],
+    boost_damage: i64,
+    max_boosts: usize,
+) -> Option<Vec<usize>> {
+    let chosen_hero = combat.chosen_hero;
+    let last_hero = heroes.last().map_or(chosen_hero, |&(hero, _)| hero);
+    let mut counts = vec![0; cmp::max(last_hero, chosen_hero) + 1];
+    if boost_damage == 0 {
+        return if stage.post_need <= 0 {
+            Some(counts)
+        } else {
+            None
+        };
+    }
+
+    // How many times each other hero has attacked by the time the chosen
+    // hero attacks on this turn, heaviest first. Heroes who attack equally
+    // often are interchangeable, so they are pooled, caps and all.
+    let mut others: Vec<(usize, i64)> = heroes
+        .iter()
+        .filter(|&&(hero, _)| hero != chosen_hero)
+        .map(|&(hero, turns)| {
+            let weight = combat.attacks_before_chosen(hero, turns, stage.turn);
+            (hero, weight as i64)
+        })
+        .filter(|&(hero, weight)| weight > 0 && combat.caps[hero] > 0)
+        .collect();
+    others.sort_by_key(|&(_, weight)| cmp::Reverse(weight));
+    let mut pools: Vec<(i64, usize)> = Vec::new();
+    for &(hero, weight) in &others {
+        match pools.last_mut() {
+            Some(pool) if pool.0 == weight => pool.1 = pool.1.saturating_add(combat.caps[hero]),
+            _ => pools.push((weight, combat.caps[hero])),
+        }
+    }
+
+    let turn = i64::try_from(stage.turn).unwrap_or(i64::MAX);
+    let max_units = stage.pre_limit / boost_damage;
+    let needed_units = ceil_div(stage.post_need, boost_damage);
+
+    let max_chosen_boosts = if heroes.iter().any(|&(hero, _)| hero == chosen_hero) {
+        cmp::min(max_boosts, combat.caps[chosen_hero])
+    } else {
+        0
+    };
+    // Each boost on the chosen hero narrows the gap between `lo` and `hi`
+    // below by one, so we can skip straight to where it closes.
+    let first_chosen_boosts = usize::try_from(needed_units.saturating_sub(max_units)).unwrap_or(0);
+    for chosen_boosts in first_chosen_boosts..=max_chosen_boosts {
+        let k = i64::try_from(chosen_boosts).unwrap_or(i64::MAX);
+        // Each boost on the chosen hero counts `turn` times before their
+        // attack on this turn and `turn + 1` times after it. These are only
+        // bounds, so saturating keeps them right: `hi` stops the loop once it
+        // is negative, and an `lo` that low is as good as zero.
+        let hi = max_units.saturating_sub(turn.saturating_mul(k));
+        let lo = needed_units.saturating_sub(turn.saturating_add(1).saturating_mul(k));
+        if hi < 0 {
+            break;
+        }
+        if lo > hi {
+            continue;
+        }
+        let mut pool_counts = vec![0; pools.len()];
+        if fill(&pools, max_boosts - chosen_boosts, lo, hi, &mut pool_counts) {
+            // Hand out each pool's boosts to its heroes, front to back.
+            let mut pool = 0;
+            for (i, &(hero, weight)) in others.iter().enumerate() {
+                if i > 0 && others[i - 1].1 != weight {
+                    pool += 1;
+                }
+                counts[hero] = cmp::min(pool_counts[pool], combat.caps[hero]);
+                pool_counts[pool] -= counts[hero];
+            }
+            counts[chosen_hero] = chosen_boosts;
+            return Some(counts);
+        }
+    }
+    None
+}
+
+// Picks boost counts for the given (weight, cap) pools, sorted by decreasing
+// weight, so that the weighted sum lies in [lo, hi] using at most `budget`
+// boosts. The counts line up with the pools.
+fn fill(pools: &[(i64, usize)], budget: usize, lo: i64, hi: i64, counts: &mut [usize]) -> bool {
+    if lo <= 0 {
+        return hi >= 0;
+    }
+    let index = counts.len() - pools.len();
+    let (&(weight, cap), rest) = match pools.split_first() {
+        Some(split) => split,
+        None => return false,
+    };
+    // No pool further down can do better than this one.
+    if weight.saturating_mul(i64::try_from(budget).unwrap_or(i64::MAX)) < lo {
+        return false;
+    }
+    // If the window is at least as wide as the weight, we can't step over it.
+    let least = ceil_div(lo, weight) as usize;
+    if hi - lo + 1 >= weight && least <= cap {
+        counts[index] = least;
+        return true;
+    }
+    let most = i64::try_from(cmp::min(budget, cap)).unwrap_or(i64::MAX);
+    let max_count = cmp::min(most, hi / weight) as usize;
+    for count in (0..=max_count).rev() {
+        let damage = count as i64 * weight;
+        if fill(rest, budget - count, lo - damage, hi - damage, counts) {
+            counts[index] = count;
+            return true;
+        }
+    }
+    false
+}
+
+fn expand_counts(counts: &[usize]) -> Vec<usize> {
+    counts
+        .iter()
+        .enumerate()
+        .flat_map(|(hero, &count)| (0..count).map(move |_| hero))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use problem::{Hero, Rules};
+    use std::time::Duration;
+
+    #[test]
+    fn skips_turns_that_cannot_work() {
+        // Stepping through every turn here would take seconds.
+        let report = NativeBackend
+            .solve(
+                &Problem {
+                    monster_health: 2000000000,
+                    heroes: vec![Hero::new(3000000000, 1)],
+                    chosen_hero: 0,
+                    boost_damage: 1,
+                    max_boosts: 1,
+                    rules: Rules::default(),
+                },
+                &SolveOptions::default(),
+            )
+            .unwrap();
+        assert_eq!(Some(999999999), report.turn);
+        assert_eq!(Some(vec![0]), report.boosts);
+    }
+
+    #[test]
+    fn limits_keep_the_best_so_far() {
+        // Without boosts the hero takes ten turns, with four just two.
+        let problem = Problem {
+            monster_health: 10,
+            heroes: vec![Hero::new(100, 1)],
+            chosen_hero: 0,
+            boost_damage: 1,
+            max_boosts: 5,
+            rules: Rules::default(),
+        };
+        let solve = |time_limit, node_limit| {
+            let options = SolveOptions {
+                objective: Objective::FewestBoosts,
+                time_limit,
+                node_limit,
+                ..SolveOptions::default()
+            };
+            let report = NativeBackend.solve(&problem, &options).unwrap();
+            (report.status, report.boosts)
+        };
+        assert_eq!((SolveStatus::Solved, Some(vec![])), solve(None, None));
+        assert_eq!((SolveStatus::Unknown, None), solve(None, Some(0)));
+        // The first search finds the kill with four boosts, the second
+        // halves that, and there the bisection stops.
+        assert_eq!(
+            (SolveStatus::Unknown, Some(vec![0, 0])),
+            solve(None, Some(2))
+        );
+        assert_eq!(
+            (SolveStatus::TimedOut, None),
+            solve(Some(Duration::from_secs(0)), None)
+        );
+    }
+
+    #[test]
+    fn health_boost_keeps_the_chosen_hero_alive() {
+        // Unboosted, the hero only attacks once.
+        let mut problem = Problem {
+            monster_health: 5,
+            heroes: vec![Hero::new(1, 2)],
+            chosen_hero: 0,
+            boost_damage: 1,
+            max_boosts: 0,
+            rules: Rules {
+                health_boost: 2,
+                max_health_boosts: 2,
+                ..Rules::default()
+            },
+        };
+        let report = NativeBackend
+            .solve(&problem, &SolveOptions::default())
+            .unwrap();
+        assert_eq!(Some(2), report.turn);
+        assert_eq!(Some(vec![]), report.boosts);
+        assert_eq!(Some(vec![0]), report.health_boosts);
+
+        problem.rules.boost_caps = vec![0];
+        let report = NativeBackend
+            .solve(&problem, &SolveOptions::default())
+            .unwrap();
+        assert_eq!(None, report.boosts);
+    }
+
+    #[test]
+    fn capped_chosen_hero_needs_help_from_behind() {
+        // The chosen hero can only take one of the two boosts, so the other
+        // goes to the hero behind, who outlives them.
+        let problem = Problem {
+            monster_health: 9,
+            heroes: vec![Hero::new(2, 1), Hero::new(5, 1)],
+            chosen_hero: 0,
+            boost_damage: 2,
+            max_boosts: 2,
+            rules: Rules {
+                boost_caps: vec![1],
+                ..Rules::default()
+            },
+        };
+        let report = NativeBackend
+            .solve(&problem, &SolveOptions::default())
+            .unwrap();
+        assert_eq!(Some(vec![0, 1]), report.boosts);
+        assert_eq!(None, report.health_boosts);
+    }
+
+    #[test]
+    fn boosts_overflow() {
+        let problem = Problem {
+            monster_health: 10,
+            heroes: vec![Hero::new(1, 1)],
+            chosen_hero: 0,
+            boost_damage: i64::MAX / 2,
+            max_boosts: 3,
+            rules: Rules::default(),
+        };
+        match NativeBackend.solve(&problem, &SolveOptions::default()) {
+            Err(MonsterError::Overflow(_)) => {}
+            other => panic!("Expected an overflow, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn fill_exact_sum() {
+        let mut counts = vec![0; 2];
+        assert!(fill(
+            &[(5, usize::MAX), (3, usize::MAX)],
+            3,
+            11,
+            11,
+            &mut counts
+        ));
+        assert_eq!(vec![1, 2], counts);
+    }
+
+    #[test]
+    fn fill_over_budget() {
+        let mut counts = vec![0; 2];
+        assert!(!fill(
+            &[(5, usize::MAX), (3, usize::MAX)],
+            2,
+            11,
+            11,
+            &mut counts
+        ));
+    }
+
+    #[test]
+    fn fill_within_caps() {
+        let mut counts = vec![0; 2];
+        assert!(fill(&[(5, 1), (3, usize::MAX)], 4, 12, 12, &mut counts));
+        assert_eq!(vec![0, 4], counts);
+        assert!(!fill(&[(5, 1), (3, 3)], 4, 12, 12, &mut counts));
+        // A wide window is no help if the cap stops us from reaching it.
+        assert!(fill(&[(5, 1), (1, 2)], 3, 7, 10, &mut counts));
+        assert_eq!(vec![1, 2], counts);
+    }
+
+    #[test]
+    fn combinations_in_order() {
+        let mut indices = vec![0, 1];
+        let mut seen = vec![indices.clone()];
+        while next_combination(&mut indices, 4) {
+            seen.push(indices.clone());
+        }
+        assert_eq!(
+            vec![
+                vec![0, 1],
+                vec![0, 2],
+                vec![0, 3],
+                vec![1, 2],
+                vec![1, 3],
+                vec![2, 3]
+            ],
+            seen
+        );
+        assert!(!next_combination(&mut [], 3));
+    }
+
+    #[test]
+    fn various_expand_counts() {
+        assert!(expand_counts(&[]).is_empty());
+        assert_eq!(vec![0, 2, 2], expand_counts(&[1, 0, 2]));
+    }
+}
@@ -0,0 +1,284 @@
+use backend::MonsterSolver;
+use format::{parse_json, read_problem};
+use problem::Problem;
+use serde::Serialize;
+use simulation::solution_is_valid_with_health;
+use solution::{solve_with, SolveOptions};
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+// One problem to solve, or the reason it couldn't be read. Either way it gets
+// a result, so that a bad instance shows up in the output without stopping
+// the run.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BatchItem {
+    // Where the problem came from: a file name, or a line of a stream.
+    pub name: String,
+    pub problem: Result<Problem, String>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct BatchResult {
+    pub name: String,
+    // "solved", "infeasible", "timed_out", "unknown" or "error".
+    pub status: String,
+    pub boosts: Option<Vec<usize>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub health_boosts: Option<Vec<usize>>,
+    pub turn: Option<usize>,
+    // Whether the simulation agrees with the boosts, if there are any.
+    pub valid: Option<bool>,
+    pub elapsed_ms: f64,
+    pub error: Option<String>,
+    // What the backend had to add, such as why a search stopped by a limit
+    // found nothing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl BatchResult {
+    pub fn is_error(&self) -> bool {
+        self.error.is_some()
+    }
+
+    fn error(name: String, elapsed_ms: f64, message: String) -> BatchResult {
+        BatchResult {
+            name,
+            status: "error".to_string(),
+            boosts: None,
+            health_boosts: None,
+            turn: None,
+            valid: None,
+            elapsed_ms,
+            error: Some(message),
+            note: None,
+        }
+    }
+}
+
+// The problems in the files of a directory, in either format, in order of
+// file name. Subdirectories are skipped.
+pub fn read_dir(dir: &Path) -> io::Result<impl Iterator<Item = BatchItem>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths.into_iter().map(|path| BatchItem {
+        name: path.display().to_string(),
+        problem: File::open(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|file| read_problem(file).map_err(|e| e.to_string())),
+    }))
+}
+
+// The problems in a stream of JSON objects, one per line, named after the
+// source and the line. Blank lines are skipped. Reading stops at the first
+// error from the stream itself, which is given as the last item.
+pub fn read_jsonl<R: BufRead>(source: &str, reader: R) -> JsonLines<R> {
+    JsonLines {
+        source: source.to_string(),
+        lines: reader.lines(),
+        line: 0,
+        done: false,
+    }
+}
+
+pub struct JsonLines<R> {
+    source: String,
+    lines: io::Lines<R>,
+    line: usize,
+    done: bool,
+}
+
+impl<R: BufRead> Iterator for JsonLines<R> {
+    type Item = BatchItem;
+
+    fn next(&mut self) -> Option<BatchItem> {
+        while !self.done {
+            self.line += 1;
+            let name = format!("{}:{}", self.source, self.line);
+            let problem = match self.lines.next()? {
+                Ok(ref line) if line.trim().is_empty() => continue,
+                Ok(line) => parse_json(&line).map_err(|e| e.to_string()),
+                Err(e) => {
+                    self.done = true;
+                    Err(e.to_string())
+                }
+            };
+            return Some(BatchItem { name, problem });
+        }
+        None
+    }
+}
+
+// Solves a single item, turning every failure into an error result.
+pub fn solve_item(
+    item: BatchItem,
+    solver: &dyn MonsterSolver,
+    options: &SolveOptions,
+) -> BatchResult {
+    let start = Instant::now();
+    let elapsed_ms = |start: Instant| start.elapsed().as_secs_f64() * 1000.0;
+    let problem = match item.problem {
+        Ok(problem) => problem,
+        Err(message) => return BatchResult::error(item.name, elapsed_ms(start), message),
+    };
+    let report = match solve_with(&problem, solver, options) {
+        Ok(report) => report,
+        Err(e) => return BatchResult::error(item.name, elapsed_ms(start), e.to_string()),
+    };
+    let health_boosts = report.health_boosts.as_deref().unwrap_or(&[]);
+    let valid = match report.boosts {
+        Some(ref boosts) => match solution_is_valid_with_health(&problem, boosts, health_boosts) {
+            Ok(valid) => Some(valid),
+            Err(e) => return BatchResult::error(item.name, elapsed_ms(start), e.to_string()),
+        },
+        None => None,
+    };
+    BatchResult {
+        name: item.name,
+        status: report.status.to_string(),
+        boosts: report.boosts,
+        health_boosts: report.health_boosts,
+        turn: report.turn,
+        valid,
+        elapsed_ms: report.elapsed.as_secs_f64() * 1000.0,
+        error: None,
+        note: report.note,
+    }
+}
+
+// Solves the items on `threads` worker threads, and hands the results to
+// `sink` in the order of the items, as soon as all those before are done.
+// Items are only taken from the iterator as workers become free, so a long
+// stream is never read in full.
+pub fn solve_batch<I, F>(
+    items: I,
+    solver: &(dyn MonsterSolver + Sync),
+    options: &SolveOptions,
+    threads: usize,
+    mut sink: F,
+) where
+    I: Iterator<Item = BatchItem> + Send,
+    F: FnMut(BatchResult),
+{
+    let items = Mutex::new(items.enumerate());
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            let sender = sender.clone();
+            let items = &items;
+            scope.spawn(move || loop {
+                // Another worker panicking while holding the lock leaves the
+                // iterator as it was, so carry on with it.
+                let next = items
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .next();
+                let (index, item) = match next {
+                    Some(next) => next,
+                    None => break,
+                };
+                let name = item.name.clone();
+                let start = Instant::now();
+                let result =
+                    panic::catch_unwind(AssertUnwindSafe(|| solve_item(item, solver, options)))
+                        .unwrap_or_else(|_| {
+                            let elapsed_ms = start.elapsed().as_secs_f64() * 1000.0;
+                            BatchResult::error(name, elapsed_ms, "the solver panicked".to_string())
+                        });
+                if sender.send((index, result)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        let mut pending = BTreeMap::new();
+        let mut next_index = 0;
+        for (index, result) in receiver {
+            pending.insert(index, result);
+            while let Some(result) = pending.remove(&next_index) {
+                sink(result);
+                next_index += 1;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use backend::NativeBackend;
+    use problem::{Hero, Rules};
+    use serde_json;
+
+    fn problem(monster_health: i64) -> Problem {
+        Problem {
+            monster_health,
+            heroes: vec![Hero::new(3, 1)],
+            chosen_hero: 0,
+            boost_damage: 1,
+            max_boosts: 1,
+            rules: Rules::default(),
+        }
+    }
+
+    #[test]
+    fn jsonl_lines() {
+        let input = format!(
+            "{}\n\nnot json\n{}\n",
+            serde_json::to_string(&problem(2)).unwrap(),
+            serde_json::to_string(&problem(3)).unwrap()
+        );
+        let items: Vec<BatchItem> = read_jsonl("in", input.as_bytes()).collect();
+        let names: Vec<&str> = items.iter().map(|item| item.name.as_str()).collect();
+        assert_eq!(vec!["in:1", "in:3", "in:4"], names);
+        assert_eq!(Ok(problem(2)), items[0].problem);
+        assert!(items[1].problem.is_err());
+        assert_eq!(Ok(problem(3)), items[2].problem);
+    }
+
+    #[test]
+    fn results_in_order() {
+        let items = (0..20).map(|i| BatchItem {
+            name: i.to_string(),
+            problem: if i == 5 {
+                Err("unreadable".to_string())
+            } else {
+                Ok(problem(i + 1))
+            },
+        });
+        let mut results = Vec::new();
+        solve_batch(
+            items,
+            &NativeBackend,
+            &SolveOptions::default(),
+            4,
+            |result| results.push(result),
+        );
+        let names: Vec<String> = results.iter().map(|result| result.name.clone()).collect();
+        let expected: Vec<String> = (0..20).map(|i: i64| i.to_string()).collect();
+        assert_eq!(expected, names);
+
+        assert_eq!("error", results[5].status);
+        assert_eq!(Some("unreadable".to_string()), results[5].error);
+        // Three attacks, each boosted, deal at most 6 damage.
+        assert_eq!("solved", results[4].status);
+        assert_eq!(Some(true), results[4].valid);
+        assert_eq!("infeasible", results[6].status);
+        assert_eq!(None, results[6].valid);
+    }
+}
@@ -1,49 +1,998 @@
 extern crate rust_monster;
-use rust_monster::problem::{Hero, Problem};
-use rust_monster::solution::solve;
-use rust_monster::simulation::solution_is_valid;
-
-// Solution to
-// https://gist.github.com/1Computer1/125ab56958ba15ac625d78a5a08df9e0
-// We make the following modifications:
-// * Initial monster turn is skipped.
+extern crate serde_json;
+use rust_monster::backend::{
+    Backend, MonsterSolver, BRUTE_FORCE_MAX_BOOSTS, BRUTE_FORCE_MAX_HEROES,
+};
+use rust_monster::batch::{read_dir, read_jsonl, solve_batch, BatchItem};
+use rust_monster::campaign::{read_campaign, simulate_campaign, solve_campaign};
+use rust_monster::enumerate::{count_distributions, distributions, Count, Distribution};
+use rust_monster::error::MonsterError;
+use rust_monster::explain::explain_infeasibility;
+use rust_monster::format::{read_problem, Format};
+use rust_monster::fuzz::fuzz;
+use rust_monster::generate::{Generator, GeneratorConfig};
+use rust_monster::monte_carlo::{estimate_win_probability, most_likely_boosts};
+use rust_monster::problem::Problem;
+use rust_monster::reachability::reachability;
+use rust_monster::schedule::solve_schedule;
+use rust_monster::sensitivity::{feasible_region, min_boost_damage, min_max_boosts};
+use rust_monster::simulation::{
+    schedule_is_valid, simulate_schedule, simulate_trace_with_health, simulate_with_health,
+    solution_is_valid, solution_is_valid_with_health,
+};
+use rust_monster::solution::{solve_with, Objective, SolveOptions, SolveStatus};
+
+use serde_json::{json, Map, Value};
+
+use std::env;
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+
+const USAGE: &str = "\
+usage: rust_monster_solver <command> [OPTIONS] [FILE...]
+
+commands:
+  solve      find boosts that let the chosen hero land the killing blow, or
+             explain turn by turn why there are none
+  validate   check whether --boosts lets the chosen hero land the killing blow
+  simulate   print the outcome of the combat with --boosts applied
+  enumerate  list every boost distribution that lets the chosen hero land
+             the killing blow
+  sensitivity
+             find the smallest max_boosts and boost_damage that let the
+             chosen hero land the killing blow
+  reach      find out for every hero whether boosts can let them land the
+             killing blow, and which
+  batch      solve many problems on several threads and print one JSON
+             result per line; each FILE is a directory of problems or a
+             stream of JSON problems, one per line
+  generate   print random problems
+  fuzz       check a backend against the simulation on random problems and
+             print the problems it gets wrong, shrunk, as tests
+  campaign   plan the fewest boosts that win a campaign of fights against
+             one monster after another; each FILE is a campaign in JSON
+  odds       estimate how likely the chosen hero is to land the killing
+             blow when heroes deal random damage, with --boosts or with the
+             boosts that make it likeliest
+
+Problems are read from the given files, or from stdin if there are none or
+for -. Boost lists are comma-separated hero indices, e.g. --boosts 0,0,2, and
+schedules are comma-separated turn:hero pairs, e.g. --schedule 0:2,3:1.
+Campaign plans are boost lists for each fight separated by /, e.g.
+--plan 0,1//2 for a boost each for heroes 0 and 1 in the first fight, none in
+the second and one for hero 2 in the third.
+
+options:
+  --json                  print one JSON object per line instead of text
+  --backend NAME          (solve, batch, fuzz) native (default), cbc, or brute_force
+                          for small problems
+  --lp-file PATH          (solve) write the LP model of the cbc backend to PATH
+  --objective NAME        (solve, reach, batch) any (default), fewest_boosts,
+                          earliest_kill or fewest_heroes
+  --time-limit SECONDS, --node-limit N
+                          (solve, batch) stop the backend's search after
+                          this long or after this many nodes, with the best
+                          boosts found so far, if any
+  --boosts LIST           (validate, simulate, odds) boosts to apply, none by
+                          default
+  --health-boosts LIST    (validate, simulate) health boosts to apply, none by
+                          default
+  --schedule LIST         (validate, simulate) boosts to apply during combat,
+                          each from the start of its turn on, instead
+  --scheduled             (solve) find a schedule of boosts, keeping to the
+                          problem's boosts_per_turn, instead; this always
+                          finds the earliest kill with the native solver
+  --trace                 (simulate) also print every turn of the combat
+  --plan PLAN             (campaign) fight the campaign with these boosts
+                          instead of planning them
+  --limit N               (enumerate) stop after N distributions
+  --count-only            (enumerate) only count the distributions
+  --csv PATH              (sensitivity) write which combinations of max_boosts
+                          and boost_damage work to PATH, as a grid
+  --grid-boosts N, --grid-damage N
+                          (sensitivity) the largest max_boosts and
+                          boost_damage in the grid, by default twice the
+                          problem's
+  --threads N             (batch) number of worker threads, by default one
+                          per processor
+  --trials N              (odds) number of simulated combats for each
+                          estimate, 10000 by default
+  --seed N                (generate, fuzz, odds) random seed, 0 by default
+  --count N               (generate, fuzz) number of problems, 1 by default
+                          for generate and 1000 for fuzz
+  --out-dir DIR           (generate) write problem-<i>.txt/json files to DIR
+  --append FILE           (fuzz) also append the tests to FILE, such as
+                          tests/examples.rs
+  --min-heroes N, --max-heroes N, --max-health N, --max-damage N,
+  --max-boost-damage N, --max-boosts N, --max-speed N, --max-boosts-per-turn N
+                          (generate, fuzz) bounds for the random problems;
+                          heroes only get a speed with --max-speed, and
+                          problems a limit on boosts per turn with
+                          --max-boosts-per-turn
+  --turn-order heroes_first|monster_first
+                          (generate, fuzz) who acts first each turn
+  --monster-damage N, --monster-target front|all, --monster-growth N
+                          (generate, fuzz) the monster's attack
+
+exit status:
+  0  success
+  1  a problem has no solution, none was found within the limits, the boosts
+     don't work, or fuzz found a disagreement
+  2  usage error
+  3  a problem could not be read, is invalid, or the solver failed
+";
+
+const EXIT_OK: i32 = 0;
+const EXIT_NO: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_ERROR: i32 = 3;
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    process::exit(EXIT_USAGE);
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Command {
+    Solve,
+    Validate,
+    Simulate,
+    Enumerate,
+    Sensitivity,
+    Reach,
+    Batch,
+    Generate,
+    Fuzz,
+    Campaign,
+    Odds,
+}
+
+struct Args {
+    command: Command,
+    json: bool,
+    backend: Backend,
+    options: SolveOptions,
+    boosts: Vec<usize>,
+    // Whether --boosts was given, as odds looks for the best boosts without.
+    boosts_given: bool,
+    health_boosts: Vec<usize>,
+    schedule: Option<Vec<(usize, usize)>>,
+    scheduled: bool,
+    trace: bool,
+    plan: Option<Vec<Vec<usize>>>,
+    limit: Option<usize>,
+    count_only: bool,
+    csv: Option<PathBuf>,
+    grid_boosts: Option<usize>,
+    grid_damage: Option<i64>,
+    threads: Option<usize>,
+    trials: u64,
+    seed: u64,
+    count: Option<usize>,
+    out_dir: Option<PathBuf>,
+    append: Option<PathBuf>,
+    generator: GeneratorConfig,
+    files: Vec<String>,
+}
+
+fn value<T: FromStr>(flag: &str, arg: Option<String>) -> T
+where
+    T::Err: ToString,
+{
+    match arg.map(|a| a.parse::<T>()) {
+        Some(Ok(v)) => v,
+        Some(Err(e)) => usage_error(&format!("{}: {}", flag, e.to_string())),
+        None => usage_error(&format!("{} needs a value", flag)),
+    }
+}
+
+fn parse_boosts(list: &str) -> Result<Vec<usize>, String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|b| !b.is_empty())
+        .map(|b| b.parse().map_err(|_| format!("invalid boost {:?}", b)))
+        .collect()
+}
+
+fn parse_schedule(list: &str) -> Result<Vec<(usize, usize)>, String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|b| !b.is_empty())
+        .map(|b| {
+            let invalid = || format!("invalid scheduled boost {:?}", b);
+            let mut parts = b.splitn(2, ':');
+            let turn = parts
+                .next()
+                .and_then(|t| t.parse().ok())
+                .ok_or_else(invalid)?;
+            let hero = parts
+                .next()
+                .and_then(|h| h.parse().ok())
+                .ok_or_else(invalid)?;
+            Ok((turn, hero))
+        })
+        .collect()
+}
+
+fn parse_plan(plan: &str) -> Result<Vec<Vec<usize>>, String> {
+    plan.split('/').map(parse_boosts).collect()
+}
+
+fn parse_args() -> Args {
+    let mut args = env::args().skip(1);
+    let command = match args.next().as_deref() {
+        Some("solve") => Command::Solve,
+        Some("validate") => Command::Validate,
+        Some("simulate") => Command::Simulate,
+        Some("enumerate") => Command::Enumerate,
+        Some("sensitivity") => Command::Sensitivity,
+        Some("reach") => Command::Reach,
+        Some("batch") => Command::Batch,
+        Some("generate") => Command::Generate,
+        Some("fuzz") => Command::Fuzz,
+        Some("campaign") => Command::Campaign,
+        Some("odds") => Command::Odds,
+        Some("-h") | Some("--help") => {
+            print!("{}", USAGE);
+            process::exit(EXIT_OK);
+        }
+        Some(other) => usage_error(&format!("unknown command: {}", other)),
+        None => usage_error("missing command"),
+    };
+    let mut result = Args {
+        command,
+        json: false,
+        backend: Backend::default(),
+        options: SolveOptions::default(),
+        boosts: Vec::new(),
+        boosts_given: false,
+        health_boosts: Vec::new(),
+        schedule: None,
+        scheduled: false,
+        trace: false,
+        plan: None,
+        limit: None,
+        count_only: false,
+        csv: None,
+        grid_boosts: None,
+        grid_damage: None,
+        threads: None,
+        trials: 10000,
+        seed: 0,
+        count: None,
+        out_dir: None,
+        append: None,
+        generator: GeneratorConfig::default(),
+        files: Vec::new(),
+    };
+    let only = |flag: &str, commands: &[Command]| {
+        if !commands.contains(&command) {
+            usage_error(&format!("{} does not apply to this command", flag));
+        }
+    };
+    while let Some(arg) = args.next() {
+        let flag = arg.as_str();
+        match flag {
+            "--json" => result.json = true,
+            "--backend" => {
+                only(flag, &[Command::Solve, Command::Batch, Command::Fuzz]);
+                result.backend = value(flag, args.next());
+            }
+            "--objective" => {
+                only(flag, &[Command::Solve, Command::Reach, Command::Batch]);
+                result.options.objective = value::<Objective>(flag, args.next());
+            }
+            "--lp-file" => {
+                only(flag, &[Command::Solve]);
+                result.options.lp_file = Some(value(flag, args.next()));
+            }
+            "--time-limit" => {
+                only(flag, &[Command::Solve, Command::Batch]);
+                let seconds: f64 = value(flag, args.next());
+                match Duration::try_from_secs_f64(seconds) {
+                    Ok(limit) => result.options.time_limit = Some(limit),
+                    Err(_) => usage_error("--time-limit must be a non-negative number of seconds"),
+                }
+            }
+            "--node-limit" => {
+                only(flag, &[Command::Solve, Command::Batch]);
+                result.options.node_limit = Some(value(flag, args.next()));
+            }
+            "--boosts" => {
+                only(flag, &[Command::Validate, Command::Simulate, Command::Odds]);
+                let list: String = value(flag, args.next());
+                result.boosts = parse_boosts(&list).unwrap_or_else(|e| usage_error(&e));
+                result.boosts_given = true;
+            }
+            "--health-boosts" => {
+                only(flag, &[Command::Validate, Command::Simulate]);
+                let list: String = value(flag, args.next());
+                result.health_boosts = parse_boosts(&list).unwrap_or_else(|e| usage_error(&e));
+            }
+            "--schedule" => {
+                only(flag, &[Command::Validate, Command::Simulate]);
+                let list: String = value(flag, args.next());
+                result.schedule = Some(parse_schedule(&list).unwrap_or_else(|e| usage_error(&e)));
+            }
+            "--scheduled" => {
+                only(flag, &[Command::Solve]);
+                result.scheduled = true;
+            }
+            "--trace" => {
+                only(flag, &[Command::Simulate]);
+                result.trace = true;
+            }
+            "--plan" => {
+                only(flag, &[Command::Campaign]);
+                let plan: String = value(flag, args.next());
+                result.plan = Some(parse_plan(&plan).unwrap_or_else(|e| usage_error(&e)));
+            }
+            "--limit" => {
+                only(flag, &[Command::Enumerate]);
+                result.limit = Some(value(flag, args.next()));
+            }
+            "--count-only" => {
+                only(flag, &[Command::Enumerate]);
+                result.count_only = true;
+            }
+            "--csv" => {
+                only(flag, &[Command::Sensitivity]);
+                result.csv = Some(value(flag, args.next()));
+            }
+            "--grid-boosts" => {
+                only(flag, &[Command::Sensitivity]);
+                result.grid_boosts = Some(value(flag, args.next()));
+            }
+            "--grid-damage" => {
+                only(flag, &[Command::Sensitivity]);
+                result.grid_damage = Some(value(flag, args.next()));
+            }
+            "--threads" => {
+                only(flag, &[Command::Batch]);
+                result.threads = Some(value(flag, args.next()));
+            }
+            "--trials" => {
+                only(flag, &[Command::Odds]);
+                result.trials = value(flag, args.next());
+            }
+            "--seed" => {
+                only(flag, &[Command::Generate, Command::Fuzz, Command::Odds]);
+                result.seed = value(flag, args.next());
+            }
+            "--count" => {
+                only(flag, &[Command::Generate, Command::Fuzz]);
+                result.count = Some(value(flag, args.next()));
+            }
+            "--out-dir" => {
+                only(flag, &[Command::Generate]);
+                result.out_dir = Some(value(flag, args.next()));
+            }
+            "--append" => {
+                only(flag, &[Command::Fuzz]);
+                result.append = Some(value(flag, args.next()));
+            }
+            "--turn-order" => {
+                only(flag, &[Command::Generate, Command::Fuzz]);
+                result.generator.rules.turn_order = value(flag, args.next());
+            }
+            "--monster-damage" | "--monster-target" | "--monster-growth" => {
+                only(flag, &[Command::Generate, Command::Fuzz]);
+                let attack = &mut result.generator.rules.monster_attack;
+                match flag {
+                    "--monster-damage" => attack.damage = value(flag, args.next()),
+                    "--monster-target" => attack.target = value(flag, args.next()),
+                    _ => attack.growth = value(flag, args.next()),
+                }
+            }
+            "--min-heroes"
+            | "--max-heroes"
+            | "--max-health"
+            | "--max-damage"
+            | "--max-boost-damage"
+            | "--max-boosts"
+            | "--max-speed"
+            | "--max-boosts-per-turn" => {
+                only(flag, &[Command::Generate, Command::Fuzz]);
+                let config = &mut result.generator;
+                match flag {
+                    "--min-heroes" => config.min_heroes = value(flag, args.next()),
+                    "--max-heroes" => config.max_heroes = value(flag, args.next()),
+                    "--max-health" => config.max_health = value(flag, args.next()),
+                    "--max-damage" => config.max_damage = value(flag, args.next()),
+                    "--max-boost-damage" => config.max_boost_damage = value(flag, args.next()),
+                    "--max-boosts" => config.max_boosts = value(flag, args.next()),
+                    "--max-speed" => config.max_speed = value(flag, args.next()),
+                    _ => config.max_boosts_per_turn = value(flag, args.next()),
+                }
+            }
+            "-" => result.files.push(arg),
+            _ if arg.starts_with('-') => usage_error(&format!("unknown option: {}", arg)),
+            _ => result.files.push(arg),
+        }
+    }
+    if command == Command::Generate || command == Command::Fuzz {
+        let config = &result.generator;
+        if !result.files.is_empty() {
+            usage_error("generate and fuzz do not read problems");
+        }
+        if config.min_heroes == 0 || config.min_heroes > config.max_heroes {
+            usage_error("need 1 <= --min-heroes <= --max-heroes");
+        }
+        let attack = &config.rules.monster_attack;
+        if attack.damage == 0 && attack.growth == 0 {
+            usage_error("the monster needs --monster-damage or --monster-growth");
+        }
+        if config.max_health == 0 || config.max_damage < 0 || config.max_boost_damage < 0 {
+            usage_error("--max-health must be positive and damage bounds non-negative");
+        }
+        // Fuzzing checks every problem with the brute-force solver.
+        if command == Command::Fuzz
+            && (config.max_heroes > BRUTE_FORCE_MAX_HEROES
+                || config.max_boosts > BRUTE_FORCE_MAX_BOOSTS)
+        {
+            usage_error(&format!(
+                "fuzz needs --max-heroes <= {} and --max-boosts <= {}",
+                BRUTE_FORCE_MAX_HEROES, BRUTE_FORCE_MAX_BOOSTS
+            ));
+        }
+    } else if result.files.is_empty() {
+        result.files.push("-".to_string());
+    }
+    if result.csv.is_some() && result.files.len() > 1 {
+        usage_error("--csv takes a single problem");
+    }
+    if result.schedule.is_some() && !result.boosts.is_empty() {
+        usage_error("--boosts and --schedule don't go together");
+    }
+    if result.schedule.is_some() && !result.health_boosts.is_empty() {
+        usage_error("--health-boosts and --schedule don't go together");
+    }
+    if result.schedule.is_some() && result.trace {
+        usage_error("--trace does not support --schedule");
+    }
+    if result.scheduled
+        && (result.backend != Backend::Native
+            || result.options.lp_file.is_some()
+            || result.options.objective != Objective::default()
+            || result.options.has_limits())
+    {
+        usage_error("--scheduled takes no --backend, --lp-file, --objective or limits");
+    }
+    if result.threads == Some(0) {
+        usage_error("--threads must be positive");
+    }
+    if result.trials == 0 {
+        usage_error("--trials must be positive");
+    }
+    if result.grid_damage.is_some_and(|damage| damage < 0) {
+        usage_error("--grid-damage must not be negative");
+    }
+    result
+}
+
+// The result of running a command on one problem, as an ordered list of
+// fields so that the text output reads in a sensible order.
+struct Record {
+    fields: Vec<(&'static str, Value)>,
+    // False if the answer is negative: no solution, or boosts that don't work.
+    ok: bool,
+    // Printed after the fields, or as one more field in JSON.
+    table: Option<Table>,
+}
+
+struct Table {
+    key: &'static str,
+    json: Value,
+    text: String,
+}
+
+impl Record {
+    fn new(ok: bool) -> Record {
+        Record {
+            fields: Vec::new(),
+            ok,
+            table: None,
+        }
+    }
+
+    fn add(mut self, key: &'static str, value: Value) -> Record {
+        self.fields.push((key, value));
+        self
+    }
+}
+
+fn text_value(value: &Value) -> String {
+    match *value {
+        Value::Null => "none".to_string(),
+        Value::String(ref s) => s.clone(),
+        // Pairs, such as scheduled boosts, are written as on the command line.
+        Value::Array(ref values) => values
+            .iter()
+            .map(|value| match *value {
+                Value::Array(ref pair) => pair.iter().map(text_value).collect::<Vec<_>>().join(":"),
+                ref other => text_value(other),
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+        Value::Object(ref map) => map
+            .iter()
+            .map(|(key, value)| format!("{} {}", key, text_value(value)))
+            .collect::<Vec<_>>()
+            .join(" "),
+        ref other => other.to_string(),
+    }
+}
+
+fn print_record(file: &str, record: Record, json: bool) {
+    if json {
+        let mut object = Map::new();
+        if !file.is_empty() {
+            object.insert("file".to_string(), json!(file));
+        }
+        for (key, value) in record.fields {
+            object.insert(key.to_string(), value);
+        }
+        if let Some(table) = record.table {
+            object.insert(table.key.to_string(), table.json);
+        }
+        println!("{}", Value::Object(object));
+    } else {
+        for (key, value) in record.fields {
+            match value {
+                // Lists of records get a line each.
+                Value::Array(ref items) if items.iter().any(Value::is_object) => {
+                    println!("{}:", key);
+                    for item in items {
+                        println!("  {}", text_value(item));
+                    }
+                }
+                _ => println!("{}: {}", key, text_value(&value)),
+            }
+        }
+        if let Some(table) = record.table {
+            print!("{}", table.text);
+        }
+    }
+}
+
+fn load(file: &str) -> Result<Problem, Box<dyn Error>> {
+    let problem = if file == "-" {
+        read_problem(io::stdin())?
+    } else {
+        read_problem(File::open(file)?)?
+    };
+    Ok(problem)
+}
+
+fn run_solve(problem: &Problem, args: &Args) -> Result<Record, Box<dyn Error>> {
+    if args.scheduled {
+        return run_solve_schedule(problem);
+    }
+    let report = solve_with(problem, &args.backend, &args.options)?;
+    let health_boosts = report.health_boosts.as_deref().unwrap_or(&[]);
+    let valid = match report.boosts {
+        Some(ref boosts) => Some(solution_is_valid_with_health(
+            problem,
+            boosts,
+            health_boosts,
+        )?),
+        None => None,
+    };
+    let mut record = Record::new(report.boosts.is_some())
+        .add("status", json!(report.status.to_string()))
+        .add("objective", json!(report.objective.to_string()))
+        .add("boosts", json!(report.boosts));
+    if problem.has_health_boosts() {
+        record = record.add("health_boosts", json!(report.health_boosts));
+    }
+    record = record
+        .add("turn", json!(report.turn))
+        .add("valid", json!(valid))
+        .add("trivial", json!(solution_is_valid(problem, &[])?))
+        .add("backend", json!(report.backend))
+        .add("elapsed_ms", json!(report.elapsed.as_secs_f64() * 1000.0));
+    if let Some(ref note) = report.note {
+        record = record.add("note", json!(note));
+    }
+    if let Some(size) = report.model_size {
+        record = record
+            .add("variables", json!(size.variables))
+            .add("constraints", json!(size.constraints));
+    }
+    if report.status == SolveStatus::Infeasible {
+        // The explanation only covers plain damage boosts.
+        match explain_infeasibility(problem) {
+            Err(MonsterError::Unsupported(_)) => {}
+            reasons => record = record.add("infeasibility", json!(reasons?)),
+        }
+    }
+    Ok(record)
+}
+
+fn run_solve_schedule(problem: &Problem) -> Result<Record, Box<dyn Error>> {
+    let solution = solve_schedule(problem)?;
+    let valid = match solution {
+        Some(ref solution) => Some(schedule_is_valid(problem, &solution.schedule)?),
+        None => None,
+    };
+    Ok(Record::new(solution.is_some())
+        .add(
+            "status",
+            json!(if solution.is_some() {
+                "solved"
+            } else {
+                "infeasible"
+            }),
+        )
+        .add("schedule", json!(solution.as_ref().map(|s| &s.schedule)))
+        .add("turn", json!(solution.as_ref().map(|s| s.turn)))
+        .add("valid", json!(valid)))
+}
+
+fn run_validate(problem: &Problem, args: &Args) -> Result<Record, Box<dyn Error>> {
+    if let Some(ref schedule) = args.schedule {
+        let valid = schedule_is_valid(problem, schedule)?;
+        return Ok(Record::new(valid)
+            .add("schedule", json!(schedule))
+            .add("valid", json!(valid)));
+    }
+    let valid = solution_is_valid_with_health(problem, &args.boosts, &args.health_boosts)?;
+    let mut record = Record::new(valid).add("boosts", json!(args.boosts));
+    if !args.health_boosts.is_empty() {
+        record = record.add("health_boosts", json!(args.health_boosts));
+    }
+    Ok(record.add("valid", json!(valid)))
+}
+
+fn run_simulate(problem: &Problem, args: &Args) -> Result<Record, Box<dyn Error>> {
+    let (outcome, trace) = if let Some(ref schedule) = args.schedule {
+        (simulate_schedule(problem, schedule)?, None)
+    } else if args.trace {
+        let trace = simulate_trace_with_health(problem, &args.boosts, &args.health_boosts)?;
+        (trace.outcome.clone(), Some(trace))
+    } else {
+        (
+            simulate_with_health(problem, &args.boosts, &args.health_boosts)?,
+            None,
+        )
+    };
+    let mut record = Record::new(true);
+    record = match args.schedule {
+        Some(ref schedule) => record.add("schedule", json!(schedule)),
+        None => record.add("boosts", json!(args.boosts)),
+    };
+    if !args.health_boosts.is_empty() {
+        record = record.add("health_boosts", json!(args.health_boosts));
+    }
+    record = record
+        .add("killer", json!(outcome.killer))
+        .add("turn", json!(outcome.turn))
+        .add("monster_health", json!(outcome.monster_health))
+        .add(
+            "chosen_hero_wins",
+            json!(outcome.killer == Some(problem.chosen_hero)),
+        );
+    record.table = trace.map(|trace| Table {
+        key: "trace",
+        json: json!(trace),
+        text: trace.to_table(),
+    });
+    Ok(record)
+}
+
+fn run_enumerate(problem: &Problem, args: &Args) -> Result<Record, Box<dyn Error>> {
+    let (count, listed) = if args.count_only {
+        (count_distributions(problem, args.limit)?, None)
+    } else {
+        let limit = args.limit.unwrap_or(usize::MAX);
+        let listed: Vec<Distribution> = distributions(problem)?.take(limit).collect();
+        let count = if listed.len() >= limit {
+            Count::AtLeast(listed.len())
+        } else {
+            Count::Exact(listed.len())
+        };
+        (count, Some(listed))
+    };
+    let (count, complete) = match count {
+        Count::Exact(count) => (count, true),
+        Count::AtLeast(count) => (count, false),
+    };
+    let mut record = Record::new(count > 0)
+        .add("count", json!(count))
+        .add("complete", json!(complete));
+    if let Some(listed) = listed {
+        record = record.add("distributions", json!(listed));
+    }
+    Ok(record)
+}
+
+fn run_sensitivity(problem: &Problem, args: &Args) -> Result<Record, Box<dyn Error>> {
+    let max_boosts = min_max_boosts(problem)?;
+    let boost_damage = min_boost_damage(problem)?;
+    let mut record = Record::new(max_boosts.is_some() || boost_damage.is_some())
+        .add("min_max_boosts", json!(max_boosts))
+        .add("min_boost_damage", json!(boost_damage));
+    if let Some(ref path) = args.csv {
+        let grid_boosts = args
+            .grid_boosts
+            .unwrap_or_else(|| problem.max_boosts.saturating_mul(2));
+        let grid_damage = args
+            .grid_damage
+            .unwrap_or_else(|| problem.boost_damage.saturating_mul(2));
+        let region = feasible_region(problem, grid_boosts, grid_damage)?;
+        fs::write(path, region.to_csv())?;
+        record = record.add("csv", json!(path.display().to_string()));
+    }
+    Ok(record)
+}
+
+fn run_reach(problem: &Problem, args: &Args) -> Result<Record, Box<dyn Error>> {
+    let map = reachability(problem, args.options.objective)?;
+    let reachable = map.heroes.iter().filter(|blow| blow.is_reachable()).count();
+    let mut record = Record::new(reachable > 0)
+        .add("objective", json!(args.options.objective.to_string()))
+        .add("reachable", json!(reachable));
+    record.table = Some(Table {
+        key: "heroes",
+        json: json!(map),
+        text: map.to_table(),
+    });
+    Ok(record)
+}
+
+fn run_campaign(file: &str, args: &Args) -> Result<Record, Box<dyn Error>> {
+    let campaign = if file == "-" {
+        read_campaign(io::stdin())?
+    } else {
+        read_campaign(File::open(file)?)?
+    };
+    if let Some(ref plan) = args.plan {
+        let outcome = simulate_campaign(&campaign, plan)?;
+        let mut record = Record::new(outcome.won)
+            .add("won", json!(outcome.won))
+            .add("health", json!(outcome.health));
+        record.table = Some(Table {
+            key: "fights",
+            json: json!(outcome.fights),
+            text: outcome.to_table(),
+        });
+        return Ok(record);
+    }
+    let plan = solve_campaign(&campaign)?;
+    let valid = match plan {
+        Some(ref plan) => Some(simulate_campaign(&campaign, &plan.boosts())?.won),
+        None => None,
+    };
+    let mut record = Record::new(plan.is_some())
+        .add(
+            "status",
+            json!(if plan.is_some() {
+                "solved"
+            } else {
+                "infeasible"
+            }),
+        )
+        .add(
+            "total_boosts",
+            json!(plan.as_ref().map(|p| p.total_boosts())),
+        )
+        .add("valid", json!(valid));
+    record.table = plan.map(|plan| Table {
+        key: "fights",
+        json: json!(plan.fights),
+        text: plan.to_table(),
+    });
+    Ok(record)
+}
+
+fn run_odds(problem: &Problem, args: &Args) -> Result<Record, Box<dyn Error>> {
+    let (boosts, estimate, candidates) = if args.boosts_given {
+        let estimate = estimate_win_probability(problem, &args.boosts, args.trials, args.seed)?;
+        (args.boosts.clone(), estimate, None)
+    } else {
+        let best = most_likely_boosts(problem, args.trials, args.seed)?;
+        (best.boosts, best.estimate, Some(best.candidates))
+    };
+    let mut record = Record::new(estimate.wins > 0)
+        .add("boosts", json!(boosts))
+        .add("trials", json!(estimate.trials))
+        .add("wins", json!(estimate.wins))
+        .add("probability", json!(estimate.probability))
+        .add("low", json!(estimate.low))
+        .add("high", json!(estimate.high));
+    if let Some(candidates) = candidates {
+        record = record.add("candidates", json!(candidates));
+    }
+    Ok(record)
+}
+
+fn run(file: &str, args: &Args) -> Result<Record, Box<dyn Error>> {
+    // Campaigns are not problems, and are read on their own.
+    if args.command == Command::Campaign {
+        return run_campaign(file, args);
+    }
+    let problem = load(file)?;
+    match args.command {
+        Command::Solve => run_solve(&problem, args),
+        Command::Validate => run_validate(&problem, args),
+        Command::Simulate => run_simulate(&problem, args),
+        Command::Enumerate => run_enumerate(&problem, args),
+        Command::Sensitivity => run_sensitivity(&problem, args),
+        Command::Reach => run_reach(&problem, args),
+        Command::Odds => run_odds(&problem, args),
+        Command::Batch | Command::Generate | Command::Fuzz | Command::Campaign => {
+            unreachable!()
+        }
+    }
+}
+
+fn generate(args: &Args) -> Result<(), Box<dyn Error>> {
+    let format = if args.json {
+        Format::Json
+    } else {
+        Format::Text
+    };
+    let problems = Generator::new(args.seed, args.generator.clone()).take(args.count.unwrap_or(1));
+    for (i, problem) in problems.enumerate() {
+        if let Some(ref dir) = args.out_dir {
+            let extension = if args.json { "json" } else { "txt" };
+            let path = dir.join(format!("problem-{}.{}", i, extension));
+            fs::write(&path, format.write(&problem))?;
+            println!("{}", path.display());
+        } else if args.json {
+            // One problem per line, so the output can be streamed.
+            println!("{}", serde_json::to_string(&problem)?);
+        } else {
+            if i > 0 {
+                println!();
+            }
+            println!("# problem {} from seed {}", i, args.seed);
+            print!("{}", format.write(&problem));
+        }
+    }
+    Ok(())
+}
+
+fn batch_items(file: &str) -> Result<Box<dyn Iterator<Item = BatchItem> + Send>, Box<dyn Error>> {
+    Ok(if file == "-" {
+        Box::new(read_jsonl("-", BufReader::new(io::stdin())))
+    } else if Path::new(file).is_dir() {
+        Box::new(read_dir(Path::new(file))?)
+    } else {
+        Box::new(read_jsonl(file, BufReader::new(File::open(file)?)))
+    })
+}
+
+// Returns the exit status: an error on any problem takes precedence over a
+// problem without a solution.
+fn run_batch(args: &Args) -> Result<i32, Box<dyn Error>> {
+    let mut items: Box<dyn Iterator<Item = BatchItem> + Send> = Box::new(None.into_iter());
+    for file in &args.files {
+        items = Box::new(items.chain(batch_items(file)?));
+    }
+    let threads = args
+        .threads
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |threads| threads.get()));
+    let mut status = EXIT_OK;
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut write_error = None;
+    solve_batch(items, &args.backend, &args.options, threads, |result| {
+        if result.is_error() {
+            status = EXIT_ERROR;
+        } else if result.valid != Some(true) {
+            status = status.max(EXIT_NO);
+        }
+        // Keep going after a failed write, so that the workers can finish.
+        if write_error.is_none() {
+            if let Err(e) = writeln!(out, "{}", json!(result)) {
+                write_error = Some(e);
+            }
+        }
+    });
+    match write_error {
+        Some(e) => Err(e.into()),
+        None => Ok(status),
+    }
+}
+
+// Returns whether the backend agreed with the simulation on every problem.
+fn run_fuzz(args: &Args) -> Result<bool, Box<dyn Error>> {
+    let count = args.count.unwrap_or(1000);
+    let failures = fuzz(&args.backend, args.seed, count, args.generator.clone())?;
+    for failure in &failures {
+        let name = format!(
+            "fuzz_{}_seed_{}_case_{}",
+            args.backend.name(),
+            args.seed,
+            failure.case
+        );
+        let test = failure.example_test(&name);
+        if args.json {
+            let record = Record::new(false)
+                .add("case", json!(failure.case))
+                .add("disagreement", json!(failure.disagreement.to_string()))
+                .add("problem", json!(failure.problem))
+                .add("test", json!(test));
+            print_record("", record, true);
+        } else {
+            println!(
+                "case {}: {} {}",
+                failure.case,
+                args.backend.name(),
+                failure.disagreement
+            );
+            print!("{}", test);
+        }
+        if let Some(ref path) = args.append {
+            let mut file = OpenOptions::new().append(true).open(path)?;
+            write!(file, "\n{}", test)?;
+        }
+    }
+    let summary = Record::new(failures.is_empty())
+        .add("checked", json!(count))
+        .add("disagreements", json!(failures.len()));
+    print_record("", summary, args.json);
+    Ok(failures.is_empty())
+}
+
 fn main() {
-    let problem = Problem {
-        monster_health: 856867849,
-        heroes: vec![
-            Hero {
-                health: 29,
-                damage: 1910,
-            },
-            Hero {
-                health: 2112,
-                damage: 195,
-            },
-            Hero {
-                health: 43880,
-                damage: 16,
-            },
-            Hero {
-                health: 1,
-                damage: 18586,
-            },
-        ],
-        chosen_hero: 2,
-        boost_damage: 1,
-        max_boosts: 20,
-    };
-    match solve(&problem) {
-        Some(solution) => {
-            println!("success");
-            for i in &solution {
-                print!("{} ", i);
-            }
-            println!();
-            println!("Solution is valid: {}", solution_is_valid(&problem, &solution));
-        }
-        None => {
-            println!("failure");
-        }
-    }
-    println!("Trivial solution: {}", solution_is_valid(&problem, &[]));
+    let args = parse_args();
+    if args.command == Command::Batch {
+        match run_batch(&args) {
+            Ok(status) => process::exit(status),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(EXIT_ERROR);
+            }
+        }
+    }
+    if args.command == Command::Generate || args.command == Command::Fuzz {
+        let result = if args.command == Command::Generate {
+            generate(&args).map(|_| true)
+        } else {
+            run_fuzz(&args)
+        };
+        match result {
+            Ok(true) => {}
+            Ok(false) => process::exit(EXIT_NO),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(EXIT_ERROR);
+            }
+        }
+        return;
+    }
+    let mut status = EXIT_OK;
+    for file in &args.files {
+        if args.files.len() > 1 && !args.json {
+            println!("{}:", file);
+        }
+        match run(file, &args) {
+            Ok(record) => {
+                if !record.ok {
+                    status = status.max(EXIT_NO);
+                }
+                print_record(file, record, args.json);
+            }
+            Err(e) => {
+                eprintln!("{}: {}", file, e);
+                if args.json {
+                    print_record(
+                        file,
+                        Record::new(false).add("error", json!(e.to_string())),
+                        true,
+                    );
+                }
+                status = EXIT_ERROR;
+            }
+        }
+    }
+    process::exit(status);
 }
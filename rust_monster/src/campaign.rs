@@ -0,0 +1,541 @@
+// Campaigns: the same party fights one monster after another.
+//
+// The damage the heroes take carries over from one fight to the next, and
+// those who die stay dead. Boosts only last for the fight they are given in,
+// so all a fight leaves behind is how long it took: the earlier the killing
+// blow, the fewer times the monster gets to attack. Campaigns are only read
+// as JSON, the serde representation of `Campaign`:
+//
+//     {
+//       "heroes": [{"health": 5, "damage": 1}, {"health": 8, "damage": 2}],
+//       "fights": [
+//         {"monster_health": 6, "chosen_hero": 1},
+//         {"monster_health": 9, "chosen_hero": 0}
+//       ],
+//       "boost_damage": 2,
+//       "budget": {"shared": 4}
+//     }
+//
+// The budget is either `{"shared": n}`, for n boosts over the whole campaign,
+// or `{"per_fight": [n0, n1, ...]}`, for a number of boosts in each fight.
+
+use backend::kill_options;
+use error::MonsterError;
+use format::{align_columns, ParseError};
+use problem::{Hero, Problem, Rules, TurnOrder};
+use serde::{Deserialize, Serialize};
+use serde_json;
+use simulation::{simulate, Outcome};
+use solution::{build_combat, health_after};
+
+use std::collections::HashMap;
+use std::io::Read;
+
+// The solver considers every turn on which a fight could end, and every
+// state the party could be in after each fight. These keep that in check.
+pub const MAX_TURNS_PER_FIGHT: usize = 10_000;
+pub const MAX_CAMPAIGN_STATES: usize = 100_000;
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Campaign {
+    pub heroes: Vec<Hero>,
+    pub fights: Vec<Fight>,
+    pub boost_damage: i64,
+    pub budget: BoostBudget,
+    // Every monster follows the same rules, and starts its attack afresh.
+    #[serde(default)]
+    pub rules: Rules,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Fight {
+    pub monster_health: i64,
+    // The hero who must land the killing blow, by their place in the party.
+    pub chosen_hero: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BoostBudget {
+    // One pool of boosts for the whole campaign.
+    Shared(usize),
+    // The boosts for each fight, in order. What a fight doesn't use is lost.
+    PerFight(Vec<usize>),
+}
+
+impl Campaign {
+    pub fn validate(&self) -> Result<(), MonsterError> {
+        let invalid = |msg: String| Err(MonsterError::InvalidProblem(msg));
+        if self.fights.is_empty() {
+            return invalid("the campaign has no fights".to_string());
+        }
+        if let BoostBudget::PerFight(ref budgets) = self.budget {
+            if budgets.len() != self.fights.len() {
+                return invalid(format!(
+                    "{} budgets given for {} fights",
+                    budgets.len(),
+                    self.fights.len()
+                ));
+            }
+        }
+        for (i, hero) in self.heroes.iter().enumerate() {
+            if hero.health == 0 {
+                return invalid(format!("hero {} has no health", i));
+            }
+        }
+        let health = self.start_health();
+        for (i, fight) in self.fights.iter().enumerate() {
+            if fight.chosen_hero >= self.heroes.len() {
+                return invalid(format!(
+                    "chosen hero {} of fight {} does not exist, there are {} heroes",
+                    fight.chosen_hero,
+                    i,
+                    self.heroes.len()
+                ));
+            }
+            // Everything else is checked as it would be for a single fight.
+            if let Some((problem, _)) = self.fight_problem(i, &health, 0) {
+                problem.validate()?;
+                problem.reject_health_boosts("campaigns")?;
+                problem.reject_random_damage("campaigns")?;
+                problem.reject_boost_caps("campaigns")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn start_health(&self) -> Vec<usize> {
+        self.heroes.iter().map(|hero| hero.health).collect()
+    }
+
+    // The most boosts a single fight can use.
+    fn fight_budget(&self, fight: usize) -> usize {
+        match self.budget {
+            BoostBudget::Shared(boosts) => boosts,
+            BoostBudget::PerFight(ref budgets) => budgets[fight],
+        }
+    }
+
+    // A fight as a problem of its own, among the heroes with health left,
+    // along with the place in the party of each of them. None if the chosen
+    // hero is dead.
+    fn fight_problem(
+        &self,
+        fight: usize,
+        health: &[usize],
+        max_boosts: usize,
+    ) -> Option<(Problem, Vec<usize>)> {
+        let living: Vec<usize> = (0..self.heroes.len())
+            .filter(|&hero| health[hero] > 0)
+            .collect();
+        let chosen_hero = living
+            .iter()
+            .position(|&hero| hero == self.fights[fight].chosen_hero)?;
+        let problem = Problem {
+            monster_health: self.fights[fight].monster_health,
+            heroes: living
+                .iter()
+                .map(|&hero| Hero {
+                    health: health[hero],
+                    ..self.heroes[hero].clone()
+                })
+                .collect(),
+            chosen_hero,
+            boost_damage: self.boost_damage,
+            max_boosts,
+            rules: self.rules.clone(),
+        };
+        Some((problem, living))
+    }
+
+    // The party's health once a fight that ended on `turn` is over.
+    fn health_after_fight(&self, problem: &Problem, living: &[usize], turn: usize) -> Vec<usize> {
+        // The monster attacks on every turn before the killing blow, and on
+        // that one too if it goes first.
+        let attacks = match self.rules.turn_order {
+            TurnOrder::HeroesFirst => turn,
+            TurnOrder::MonsterFirst => turn + 1,
+        };
+        let mut health = vec![0; self.heroes.len()];
+        let left = health_after(&problem.heroes, &self.rules.monster_attack, attacks);
+        for (&hero, left) in living.iter().zip(left) {
+            health[hero] = left;
+        }
+        health
+    }
+}
+
+pub fn parse_campaign(input: &str) -> Result<Campaign, ParseError> {
+    serde_json::from_str(input).map_err(|e| ParseError {
+        line: Some(e.line()),
+        message: e.to_string(),
+    })
+}
+
+pub fn read_campaign<R: Read>(mut reader: R) -> Result<Campaign, ParseError> {
+    let mut input = String::new();
+    reader.read_to_string(&mut input).map_err(|e| ParseError {
+        line: None,
+        message: e.to_string(),
+    })?;
+    parse_campaign(&input)
+}
+
+// How a campaign went. Heroes are numbered by their place in the party.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct CampaignOutcome {
+    // One for every fight fought, in order. The campaign stops at the first
+    // fight lost, or before a fight whose chosen hero is dead.
+    pub fights: Vec<Outcome>,
+    pub won: bool,
+    // What is left of each hero's health after the last fight fought.
+    pub health: Vec<usize>,
+}
+
+impl CampaignOutcome {
+    pub fn to_table(&self) -> String {
+        let mut rows = vec![vec![
+            "fight".to_string(),
+            "killer".to_string(),
+            "turn".to_string(),
+            "monster_health".to_string(),
+        ]];
+        for (i, outcome) in self.fights.iter().enumerate() {
+            rows.push(vec![
+                i.to_string(),
+                outcome
+                    .killer
+                    .map_or("-".to_string(), |killer| killer.to_string()),
+                outcome.turn.to_string(),
+                outcome.monster_health.to_string(),
+            ]);
+        }
+        align_columns(&rows)
+    }
+}
+
+// Fights the campaign with the given boosts for each fight, by their place
+// in the party. Boosts that break the budget, or go to a hero who is dead by
+// then, are an error.
+pub fn simulate_campaign(
+    campaign: &Campaign,
+    plan: &[Vec<usize>],
+) -> Result<CampaignOutcome, MonsterError> {
+    campaign.validate()?;
+    let invalid = |msg: String| Err(MonsterError::InvalidBoosts(msg));
+    if plan.len() != campaign.fights.len() {
+        return invalid(format!(
+            "boosts given for {} fights, the campaign has {}",
+            plan.len(),
+            campaign.fights.len()
+        ));
+    }
+    if let BoostBudget::Shared(budget) = campaign.budget {
+        let total: usize = plan.iter().map(Vec::len).sum();
+        if total > budget {
+            return invalid(format!(
+                "{} boosts given, at most {} allowed",
+                total, budget
+            ));
+        }
+    }
+
+    let mut health = campaign.start_health();
+    let mut fights = Vec::new();
+    for (i, boosts) in plan.iter().enumerate() {
+        let (problem, living) = match campaign.fight_problem(i, &health, campaign.fight_budget(i)) {
+            Some(fight) => fight,
+            None => break,
+        };
+        let local_boosts = boosts
+            .iter()
+            .map(
+                |&boost| match living.iter().position(|&hero| hero == boost) {
+                    Some(local) => Ok(local),
+                    None if boost < campaign.heroes.len() => {
+                        Err(MonsterError::InvalidBoosts(format!(
+                            "boost for hero {} in fight {}, who is dead by then",
+                            boost, i
+                        )))
+                    }
+                    None => Err(MonsterError::InvalidBoosts(format!(
+                        "boost for hero {} who does not exist",
+                        boost
+                    ))),
+                },
+            )
+            .collect::<Result<Vec<usize>, MonsterError>>()?;
+        let outcome = simulate(&problem, &local_boosts)?;
+        let won = outcome.killer == Some(problem.chosen_hero);
+        if won {
+            health = campaign.health_after_fight(&problem, &living, outcome.turn);
+        }
+        fights.push(Outcome {
+            killer: outcome.killer.map(|killer| living[killer]),
+            ..outcome
+        });
+        if !won {
+            break;
+        }
+    }
+    Ok(CampaignOutcome {
+        won: fights.len() == campaign.fights.len()
+            && fights
+                .iter()
+                .zip(&campaign.fights)
+                .all(|(outcome, fight)| outcome.killer == Some(fight.chosen_hero)),
+        fights,
+        health,
+    })
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct PlannedFight {
+    pub hero: usize,
+    pub turn: usize,
+    pub boosts: Vec<usize>,
+}
+
+// Boosts for every fight of a campaign, with the fewest boosts in total.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct CampaignPlan {
+    pub fights: Vec<PlannedFight>,
+}
+
+impl CampaignPlan {
+    pub fn boosts(&self) -> Vec<Vec<usize>> {
+        self.fights
+            .iter()
+            .map(|fight| fight.boosts.clone())
+            .collect()
+    }
+
+    pub fn total_boosts(&self) -> usize {
+        self.fights.iter().map(|fight| fight.boosts.len()).sum()
+    }
+
+    pub fn to_table(&self) -> String {
+        let mut rows = vec![vec![
+            "fight".to_string(),
+            "hero".to_string(),
+            "turn".to_string(),
+            "boosts".to_string(),
+        ]];
+        for (i, fight) in self.fights.iter().enumerate() {
+            let boosts = if fight.boosts.is_empty() {
+                "none".to_string()
+            } else {
+                fight
+                    .boosts
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(",")
+            };
+            rows.push(vec![
+                i.to_string(),
+                fight.hero.to_string(),
+                fight.turn.to_string(),
+                boosts,
+            ]);
+        }
+        align_columns(&rows)
+    }
+}
+
+// Plans the boosts for all the fights at once, with as few as possible in
+// total. Ending a fight sooner isn't always better: a hero in front who
+// survives it may steal the next killing blow. So every turn on which each
+// fight could end is followed up, with the fewest boosts that end it then,
+// and the states the party can be in after each fight are shared.
+pub fn solve_campaign(campaign: &Campaign) -> Result<Option<CampaignPlan>, MonsterError> {
+    campaign.validate()?;
+    let mut planner = Planner {
+        campaign,
+        best: HashMap::new(),
+    };
+    let start = campaign.start_health();
+    let total = match planner.plan(0, &start)? {
+        Some(total) => total,
+        None => return Ok(None),
+    };
+    if let BoostBudget::Shared(budget) = campaign.budget {
+        if total > budget {
+            return Ok(None);
+        }
+    }
+
+    let mut fights = Vec::new();
+    let mut health = start;
+    for i in 0..campaign.fights.len() {
+        let step = planner.best[&(i, health)]
+            .clone()
+            .expect("the plan only goes through winnable states");
+        health = step.health;
+        fights.push(step.fight);
+    }
+    Ok(Some(CampaignPlan { fights }))
+}
+
+// The best way on from a state: how the next fight goes, where it leaves the
+// party, and the fewest boosts from here to the end of the campaign.
+#[derive(Clone)]
+struct Step {
+    fight: PlannedFight,
+    health: Vec<usize>,
+    total: usize,
+}
+
+struct Planner<'a> {
+    campaign: &'a Campaign,
+    // By fight and the party's health before it; None if it can't be won.
+    best: HashMap<(usize, Vec<usize>), Option<Step>>,
+}
+
+impl<'a> Planner<'a> {
+    // The fewest boosts that win the campaign from this fight on.
+    fn plan(&mut self, fight: usize, health: &[usize]) -> Result<Option<usize>, MonsterError> {
+        if fight == self.campaign.fights.len() {
+            return Ok(Some(0));
+        }
+        let key = (fight, health.to_vec());
+        if let Some(step) = self.best.get(&key) {
+            return Ok(step.as_ref().map(|step| step.total));
+        }
+        if self.best.len() >= MAX_CAMPAIGN_STATES {
+            return Err(MonsterError::TooLarge(format!(
+                "more than {} states of the party to plan from",
+                MAX_CAMPAIGN_STATES
+            )));
+        }
+
+        let campaign = self.campaign;
+        let mut best: Option<Step> = None;
+        if let Some((problem, living)) =
+            campaign.fight_problem(fight, health, campaign.fight_budget(fight))
+        {
+            let combat = build_combat(&problem)?;
+            for (turn, boosts) in kill_options(&problem, &combat, MAX_TURNS_PER_FIGHT)? {
+                if best.as_ref().is_some_and(|best| boosts.len() >= best.total) {
+                    continue;
+                }
+                let after = campaign.health_after_fight(&problem, &living, turn);
+                let rest = match self.plan(fight + 1, &after)? {
+                    Some(rest) => rest,
+                    None => continue,
+                };
+                let total = boosts.len() + rest;
+                if best.as_ref().is_none_or(|best| total < best.total) {
+                    best = Some(Step {
+                        fight: PlannedFight {
+                            hero: living[problem.chosen_hero],
+                            turn,
+                            boosts: boosts.iter().map(|&boost| living[boost]).collect(),
+                        },
+                        health: after,
+                        total,
+                    });
+                }
+            }
+        }
+        let total = best.as_ref().map(|step| step.total);
+        self.best.insert(key, best);
+        Ok(total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn campaign(budget: BoostBudget) -> Campaign {
+        Campaign {
+            heroes: vec![Hero::new(1, 1), Hero::new(3, 1)],
+            fights: vec![
+                Fight {
+                    monster_health: 3,
+                    chosen_hero: 1,
+                },
+                Fight {
+                    monster_health: 3,
+                    chosen_hero: 1,
+                },
+            ],
+            boost_damage: 1,
+            budget,
+            rules: Rules::default(),
+        }
+    }
+
+    #[test]
+    fn damage_carries_over() {
+        // The front hero dies in the first fight, so the chosen hero fights
+        // the second monster alone, and takes a hit on every turn but the
+        // last.
+        let campaign = campaign(BoostBudget::Shared(0));
+        let outcome = simulate_campaign(&campaign, &[vec![], vec![]]).unwrap();
+        assert!(outcome.won);
+        assert_eq!(vec![0, 1], outcome.health);
+        assert_eq!(
+            vec![(Some(1), 1), (Some(1), 2)],
+            outcome
+                .fights
+                .iter()
+                .map(|outcome| (outcome.killer, outcome.turn))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn plans_boosts_across_fights() {
+        // A boost that ends the first fight on the first turn keeps the front
+        // hero alive for the second, which then needs no boosts.
+        let mut campaign = campaign(BoostBudget::Shared(1));
+        campaign.fights[1].monster_health = 5;
+        let plan = solve_campaign(&campaign).unwrap().unwrap();
+        assert_eq!(1, plan.total_boosts());
+        assert!(plan.fights[1].boosts.is_empty());
+        assert_eq!(
+            vec![0, 3],
+            plan.fights.iter().map(|f| f.turn).collect::<Vec<_>>()
+        );
+        let outcome = simulate_campaign(&campaign, &plan.boosts()).unwrap();
+        assert!(outcome.won);
+
+        campaign.budget = BoostBudget::PerFight(vec![0, 0]);
+        assert_eq!(Ok(None), solve_campaign(&campaign));
+        campaign.budget = BoostBudget::PerFight(vec![1, 0]);
+        let plan = solve_campaign(&campaign).unwrap().unwrap();
+        assert_eq!(1, plan.fights[0].boosts.len());
+        campaign.budget = BoostBudget::Shared(0);
+        assert_eq!(Ok(None), solve_campaign(&campaign));
+    }
+
+    #[test]
+    fn illegal_plans() {
+        let campaign = campaign(BoostBudget::PerFight(vec![1, 0]));
+        let assert_invalid = |plan: &[Vec<usize>]| match simulate_campaign(&campaign, plan) {
+            Err(MonsterError::InvalidBoosts(_)) => {}
+            other => panic!("Expected invalid boosts, got {:?}", other),
+        };
+        assert_invalid(&[vec![]]);
+        assert_invalid(&[vec![1, 1], vec![]]);
+        assert_invalid(&[vec![2], vec![]]);
+        // The front hero is dead by the second fight.
+        let mut shared = campaign.clone();
+        shared.budget = BoostBudget::Shared(1);
+        match simulate_campaign(&shared, &[vec![], vec![0]]) {
+            Err(MonsterError::InvalidBoosts(_)) => {}
+            other => panic!("Expected invalid boosts, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn campaign_json() {
+        let campaign = campaign(BoostBudget::PerFight(vec![1, 0]));
+        let json = serde_json::to_string(&campaign).unwrap();
+        assert!(json.contains("\"per_fight\":[1,0]"));
+        assert_eq!(Ok(campaign), parse_campaign(&json));
+        assert!(parse_campaign("{\"heroes\": []}").is_err());
+    }
+}
@@ -0,0 +1,389 @@
+use error::MonsterError;
+use problem::Problem;
+use serde::Serialize;
+use solution::{attack_turns, build_combat, ceil_div, Combat, Stage, Stages};
+
+use std::cmp;
+use std::convert::TryFrom;
+
+// A boost distribution that lets the chosen hero land the killing blow, with
+// the turn on which they do. As in a SolveReport, the boosts are hero indices,
+// one per boost, in increasing order.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct Distribution {
+    pub turn: usize,
+    pub boosts: Vec<usize>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Count {
+    Exact(usize),
+    // We stopped counting at the limit; there may be more.
+    AtLeast(usize),
+}
+
+// Lists every multiset of at most `max_boosts` boosts that lets the chosen
+// hero land the killing blow, ordered by the turn on which they do. Nothing is
+// searched until the iterator is advanced, so `take` stops early.
+pub fn distributions(problem: &Problem) -> Result<Distributions, MonsterError> {
+    problem.validate()?;
+    problem.reject_health_boosts("enumeration")?;
+    problem.reject_random_damage("enumeration")?;
+    problem.reject_boost_caps("enumeration")?;
+    let combat = build_combat(problem)?;
+    Ok(Distributions {
+        stages: combat.stages(problem, problem.max_boosts)?,
+        combat,
+        attack_turns: attack_turns(&problem.heroes, &problem.rules)?,
+        boost_damage: problem.boost_damage,
+        max_boosts: problem.max_boosts,
+        turn: None,
+    })
+}
+
+// Counts the distributions without listing them, stopping once there are at
+// least `limit`. Boosts on heroes who don't attack before the killing blow
+// change nothing, so those are counted in one go rather than one by one.
+pub fn count_distributions(problem: &Problem, limit: Option<usize>) -> Result<Count, MonsterError> {
+    let limit = limit.unwrap_or(usize::MAX);
+    let mut distributions = distributions(problem)?;
+    let mut count: usize = 0;
+    while let Some(mut turn) = distributions.next_turn() {
+        while turn.next_counts() {
+            count = count.saturating_add(arrangements(turn.left(), turn.free.len()));
+            if count >= limit {
+                return Ok(Count::AtLeast(count));
+            }
+        }
+    }
+    Ok(Count::Exact(count))
+}
+
+pub struct Distributions {
+    combat: Combat,
+    stages: Stages,
+    attack_turns: Vec<usize>,
+    boost_damage: i64,
+    max_boosts: usize,
+    turn: Option<TurnSearch>,
+}
+
+impl Distributions {
+    // A boost distribution kills the monster on exactly one turn, so the
+    // searches for the different turns never overlap.
+    fn next_turn(&mut self) -> Option<TurnSearch> {
+        loop {
+            let stage = self.stages.next()?;
+            if let Some(turn) = TurnSearch::new(
+                &stage,
+                &self.combat,
+                &self.attack_turns,
+                self.boost_damage,
+                self.max_boosts,
+            ) {
+                return Some(turn);
+            }
+        }
+    }
+}
+
+impl Iterator for Distributions {
+    type Item = Distribution;
+
+    fn next(&mut self) -> Option<Distribution> {
+        loop {
+            if let Some(ref mut turn) = self.turn {
+                if let Some(boosts) = turn.next_boosts() {
+                    return Some(Distribution {
+                        turn: turn.turn,
+                        boosts,
+                    });
+                }
+            }
+            self.turn = Some(self.next_turn()?);
+        }
+    }
+}
+
+// A hero whose boosts count `pre` times before the chosen hero's attack on
+// this turn and `post` times by the end of it.
+#[derive(Debug, Clone, Copy)]
+struct Slot {
+    hero: usize,
+    pre: i64,
+    post: i64,
+}
+
+// A depth-first search over the boost counts for a single turn. The damage is
+// counted in units of boost damage.
+struct TurnSearch {
+    turn: usize,
+    // Sorted by decreasing `post`, so that the next slot is always the best
+    // place for the boosts that are left.
+    slots: Vec<Slot>,
+    // Heroes whose boosts make no difference on this turn.
+    free: Vec<usize>,
+    budget: usize,
+    max_units: i64,
+    needed_units: i64,
+    // The counts of the first `depth` slots are fixed, the rest are zero.
+    counts: Vec<usize>,
+    depth: usize,
+    used: usize,
+    pre: i64,
+    // `pre` stays within `max_units`, but the boosts on the chosen hero count
+    // once more here, which can take it past what an i64 holds.
+    post: i128,
+    started: bool,
+    free_counts: Vec<usize>,
+    in_leaf: bool,
+}
+
+impl TurnSearch {
+    fn new(
+        stage: &Stage,
+        combat: &Combat,
+        attack_turns: &[usize],
+        boost_damage: i64,
+        budget: usize,
+    ) -> Option<TurnSearch> {
+        // Like the bounds below, the weights only get compared, so capping
+        // them changes nothing.
+        let turn = i64::try_from(stage.turn).unwrap_or(i64::MAX - 1);
+        let mut slots = Vec::new();
+        let mut free = Vec::new();
+        for (hero, &turns) in attack_turns.iter().enumerate() {
+            if boost_damage == 0 {
+                free.push(hero);
+            } else if hero == combat.chosen_hero {
+                slots.push(Slot {
+                    hero,
+                    pre: turn,
+                    post: turn + 1,
+                });
+            } else {
+                match combat.attacks_before_chosen(hero, turns, stage.turn) {
+                    0 => free.push(hero),
+                    weight => slots.push(Slot {
+                        hero,
+                        pre: weight as i64,
+                        post: weight as i64,
+                    }),
+                }
+            }
+        }
+        slots.sort_by_key(|slot| cmp::Reverse(slot.post));
+
+        let (max_units, needed_units) = if boost_damage == 0 {
+            if stage.post_need > 0 {
+                return None;
+            }
+            (0, 0)
+        } else {
+            (
+                stage.pre_limit / boost_damage,
+                ceil_div(stage.post_need, boost_damage),
+            )
+        };
+        Some(TurnSearch {
+            turn: stage.turn,
+            counts: vec![0; slots.len()],
+            free_counts: vec![0; free.len()],
+            slots,
+            free,
+            budget,
+            max_units,
+            needed_units,
+            depth: 0,
+            used: 0,
+            pre: 0,
+            post: 0,
+            started: false,
+            in_leaf: false,
+        })
+    }
+
+    fn left(&self) -> usize {
+        self.budget - self.used
+    }
+
+    // Whether the boosts that are left could still bring the damage up to
+    // what is needed.
+    fn viable(&self) -> bool {
+        let best = self.slots.get(self.depth).map_or(0, |slot| slot.post);
+        self.post + i128::from(best) * self.left() as i128 >= i128::from(self.needed_units)
+    }
+
+    // Steps to the next counts on the slots that work, returning false when
+    // there are none left.
+    fn next_counts(&mut self) -> bool {
+        let mut descend = if self.started {
+            false
+        } else {
+            self.started = true;
+            self.viable()
+        };
+        loop {
+            if descend {
+                if self.depth == self.slots.len() {
+                    return true;
+                }
+                self.counts[self.depth] = 0;
+                self.depth += 1;
+                descend = self.viable();
+            } else {
+                if self.depth == 0 {
+                    return false;
+                }
+                let i = self.depth - 1;
+                let slot = self.slots[i];
+                // A boost never takes damage away, so once a slot breaks the
+                // limits so do all its larger counts.
+                if self.used < self.budget && slot.pre <= self.max_units - self.pre {
+                    self.counts[i] += 1;
+                    self.used += 1;
+                    self.pre += slot.pre;
+                    self.post += i128::from(slot.post);
+                    descend = self.viable();
+                } else {
+                    let count = self.counts[i];
+                    self.used -= count;
+                    self.pre -= count as i64 * slot.pre;
+                    self.post -= count as i128 * i128::from(slot.post);
+                    self.counts[i] = 0;
+                    self.depth -= 1;
+                }
+            }
+        }
+    }
+
+    // Steps to the next distribution, spreading what is left of the budget
+    // over the free heroes in every possible way.
+    fn next_boosts(&mut self) -> Option<Vec<usize>> {
+        let left = self.left();
+        if !(self.in_leaf && next_free_counts(&mut self.free_counts, left)) {
+            if !self.next_counts() {
+                return None;
+            }
+            self.in_leaf = true;
+            for count in &mut self.free_counts {
+                *count = 0;
+            }
+        }
+
+        let mut counts = vec![0; self.slots.len() + self.free.len()];
+        for (slot, &count) in self.slots.iter().zip(&self.counts) {
+            counts[slot.hero] = count;
+        }
+        for (&hero, &count) in self.free.iter().zip(&self.free_counts) {
+            counts[hero] = count;
+        }
+        Some(
+            counts
+                .iter()
+                .enumerate()
+                .flat_map(|(hero, &count)| (0..count).map(move |_| hero))
+                .collect(),
+        )
+    }
+}
+
+// Steps to the next way of putting at most `budget` boosts on the heroes, in
+// lexicographic order.
+fn next_free_counts(counts: &mut [usize], budget: usize) -> bool {
+    let mut used: usize = counts.iter().sum();
+    for i in (0..counts.len()).rev() {
+        if used < budget {
+            counts[i] += 1;
+            return true;
+        }
+        used -= counts[i];
+        counts[i] = 0;
+    }
+    false
+}
+
+// The number of ways to put at most `boosts` boosts on `heroes` heroes, which
+// is C(boosts + heroes, heroes), saturating.
+fn arrangements(boosts: usize, heroes: usize) -> usize {
+    let mut ways: u128 = 1;
+    for i in 1..=heroes as u128 {
+        // Each step goes from C(boosts + i - 1, i - 1) to C(boosts + i, i),
+        // and the division is exact.
+        ways = match ways.checked_mul(boosts as u128 + i) {
+            Some(product) => product / i,
+            None => return usize::MAX,
+        };
+    }
+    cmp::min(ways, usize::MAX as u128) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use problem::test_problem as problem;
+
+    #[test]
+    fn lists_distributions() {
+        let found: Vec<Distribution> = distributions(&problem()).unwrap().collect();
+        assert_eq!(
+            vec![
+                Distribution {
+                    turn: 1,
+                    boosts: vec![1, 1],
+                },
+                // The front hero only attacks once, so two boosts on them
+                // fall one short.
+                Distribution {
+                    turn: 2,
+                    boosts: vec![1],
+                },
+                Distribution {
+                    turn: 2,
+                    boosts: vec![0, 1],
+                },
+            ],
+            found
+        );
+        assert_eq!(Ok(Count::Exact(3)), count_distributions(&problem(), None));
+    }
+
+    #[test]
+    fn count_stops_at_limit() {
+        assert_eq!(
+            Ok(Count::AtLeast(2)),
+            count_distributions(&problem(), Some(2))
+        );
+        assert_eq!(
+            Ok(Count::Exact(3)),
+            count_distributions(&problem(), Some(5))
+        );
+    }
+
+    #[test]
+    fn free_boosts() {
+        // Nobody but the chosen hero attacks before the killing blow, so the
+        // other boost can go anywhere.
+        let mut problem = problem();
+        problem.monster_health = 1;
+        problem.chosen_hero = 0;
+        let found: Vec<Vec<usize>> = distributions(&problem)
+            .unwrap()
+            .map(|distribution| distribution.boosts)
+            .collect();
+        assert_eq!(
+            vec![vec![], vec![1], vec![1, 1], vec![0], vec![0, 1], vec![0, 0]],
+            found
+        );
+        assert_eq!(Ok(Count::Exact(6)), count_distributions(&problem, None));
+    }
+
+    #[test]
+    fn various_arrangements() {
+        assert_eq!(1, arrangements(5, 0));
+        assert_eq!(1, arrangements(0, 3));
+        assert_eq!(3, arrangements(2, 1));
+        assert_eq!(10, arrangements(2, 3));
+        assert_eq!(usize::MAX, arrangements(usize::MAX, 4));
+    }
+}
@@ -0,0 +1,45 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum MonsterError {
+    // The problem itself makes no sense, e.g. the chosen hero doesn't exist.
+    InvalidProblem(String),
+    // The boost vector doesn't fit the problem it is applied to.
+    InvalidBoosts(String),
+    // The backend could not be run, or gave an answer we don't understand.
+    Solver(String),
+    // Some intermediate value doesn't fit in its integer type.
+    Overflow(String),
+    // The problem is beyond what the chosen method can handle.
+    TooLarge(String),
+    // The problem uses a rule the chosen method doesn't know about.
+    Unsupported(String),
+}
+
+impl fmt::Display for MonsterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MonsterError::InvalidProblem(ref msg) => write!(f, "invalid problem: {}", msg),
+            MonsterError::InvalidBoosts(ref msg) => write!(f, "invalid boosts: {}", msg),
+            MonsterError::Solver(ref msg) => write!(f, "solver failed: {}", msg),
+            MonsterError::Overflow(ref what) => write!(f, "arithmetic overflow in {}", what),
+            MonsterError::TooLarge(ref msg) => write!(f, "problem too large: {}", msg),
+            MonsterError::Unsupported(ref msg) => write!(f, "not supported: {}", msg),
+        }
+    }
+}
+
+impl Error for MonsterError {}
+
+// Turns a failed checked operation into an error saying what overflowed, as
+// in `a.checked_add(b).or_overflow("total damage")?`.
+pub(crate) trait OrOverflow<T> {
+    fn or_overflow(self, what: &str) -> Result<T, MonsterError>;
+}
+
+impl<T> OrOverflow<T> for Option<T> {
+    fn or_overflow(self, what: &str) -> Result<T, MonsterError> {
+        self.ok_or_else(|| MonsterError::Overflow(what.to_string()))
+    }
+}
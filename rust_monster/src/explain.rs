@@ -0,0 +1,195 @@
+use backend::NativeBackend;
+use error::MonsterError;
+use problem::Problem;
+use serde::Serialize;
+use solution::{build_combat, max_boost_damage, solve_with, SolveOptions};
+
+use std::fmt;
+
+// Why the chosen hero can't land the killing blow on some turns.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+    // Even with every boost on the chosen hero, the monster survives their
+    // attack.
+    OutOfReach,
+    // Any boosts that let the chosen hero deal enough damage let the heroes
+    // attacking before them kill the monster first, or it is dead already.
+    Overkill,
+    // The chosen hero no longer attacks, and the monster is still alive.
+    ChosenHeroDies,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Reason::OutOfReach => "out_of_reach",
+            Reason::Overkill => "overkill",
+            Reason::ChosenHeroDies => "chosen_hero_dies",
+        })
+    }
+}
+
+// A reason that holds on every turn from `first_turn` to `last_turn`,
+// inclusive. Only the chosen hero dying has no last turn.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct Infeasibility {
+    pub reason: Reason,
+    pub first_turn: usize,
+    pub last_turn: Option<usize>,
+}
+
+impl fmt::Display for Infeasibility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.last_turn {
+            Some(last_turn) if last_turn == self.first_turn => {
+                write!(f, "{} on turn {}", self.reason, self.first_turn)
+            }
+            Some(last_turn) => write!(
+                f,
+                "{} on turns {} to {}",
+                self.reason, self.first_turn, last_turn
+            ),
+            None => write!(f, "{} from turn {} on", self.reason, self.first_turn),
+        }
+    }
+}
+
+// Explains why the problem has no solution, turn by turn: the reasons cover
+// every turn from the first until the chosen hero dies or the monster is dead
+// regardless, in order. Returns None if there is a solution after all.
+pub fn explain_infeasibility(
+    problem: &Problem,
+) -> Result<Option<Vec<Infeasibility>>, MonsterError> {
+    problem.validate()?;
+    problem.reject_health_boosts("the explanation")?;
+    problem.reject_random_damage("the explanation")?;
+    problem.reject_boost_caps("the explanation")?;
+    let report = solve_with(problem, &NativeBackend, &SolveOptions::default())?;
+    if report.boosts.is_some() {
+        return Ok(None);
+    }
+
+    let combat = build_combat(problem)?;
+    let max_boost_damage = max_boost_damage(problem, problem.max_boosts)?;
+    // The last phase ends when the chosen hero dies.
+    let chosen_turns = combat.phases.last().map_or(0, |phase| phase.end_stage);
+    let mut reasons = Reasons(Vec::new());
+    let mut alive_until = 0;
+    for window in combat.windows(problem)? {
+        alive_until = window.alive_until();
+        let first_reachable = window.first_reachable(combat.chosen_damage, max_boost_damage)?;
+        reasons.add(
+            Reason::OutOfReach,
+            window.start,
+            first_reachable.min(alive_until),
+        );
+        // On these turns enough damage is possible, so as there is no solution
+        // it must come too soon.
+        reasons.add(Reason::Overkill, first_reachable, alive_until);
+    }
+    if alive_until < chosen_turns {
+        reasons.add(Reason::Overkill, alive_until, chosen_turns);
+    } else {
+        reasons.0.push(Infeasibility {
+            reason: Reason::ChosenHeroDies,
+            first_turn: chosen_turns,
+            last_turn: None,
+        });
+    }
+    Ok(Some(reasons.0))
+}
+
+struct Reasons(Vec<Infeasibility>);
+
+impl Reasons {
+    // Adds the reason for the turns [start, end), merging it with the one
+    // before if that is the same.
+    fn add(&mut self, reason: Reason, start: usize, end: usize) {
+        if start >= end {
+            return;
+        }
+        if let Some(last) = self.0.last_mut() {
+            if last.reason == reason && last.last_turn == Some(start - 1) {
+                last.last_turn = Some(end - 1);
+                return;
+            }
+        }
+        self.0.push(Infeasibility {
+            reason,
+            first_turn: start,
+            last_turn: Some(end - 1),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use problem::test_problem as problem;
+    use problem::{Hero, Rules};
+
+    fn reason(reason: Reason, first_turn: usize, last_turn: Option<usize>) -> Infeasibility {
+        Infeasibility {
+            reason,
+            first_turn,
+            last_turn,
+        }
+    }
+
+    #[test]
+    fn solvable() {
+        assert_eq!(Ok(None), explain_infeasibility(&problem()));
+    }
+
+    #[test]
+    fn chosen_hero_dies() {
+        let mut problem = problem();
+        problem.monster_health = 100;
+        assert_eq!(
+            Ok(Some(vec![
+                reason(Reason::OutOfReach, 0, Some(3)),
+                reason(Reason::ChosenHeroDies, 4, None),
+            ])),
+            explain_infeasibility(&problem)
+        );
+    }
+
+    #[test]
+    fn front_hero_overkills() {
+        // The chosen hero can't deal 6 damage on the first turn, and after
+        // that the front hero kills the monster before they attack, for as
+        // long as the chosen hero lives.
+        let problem = Problem {
+            monster_health: 11,
+            heroes: vec![Hero::new(5, 5), Hero::new(5, 1)],
+            chosen_hero: 1,
+            boost_damage: 3,
+            max_boosts: 1,
+            rules: Rules::default(),
+        };
+        assert_eq!(
+            Ok(Some(vec![
+                reason(Reason::OutOfReach, 0, Some(0)),
+                reason(Reason::Overkill, 1, Some(9)),
+            ])),
+            explain_infeasibility(&problem)
+        );
+    }
+
+    #[test]
+    fn display_reasons() {
+        assert_eq!(
+            "overkill on turn 3",
+            reason(Reason::Overkill, 3, Some(3)).to_string()
+        );
+        assert_eq!(
+            "out_of_reach on turns 0 to 2",
+            reason(Reason::OutOfReach, 0, Some(2)).to_string()
+        );
+        assert_eq!(
+            "chosen_hero_dies from turn 3 on",
+            reason(Reason::ChosenHeroDies, 3, None).to_string()
+        );
+    }
+}
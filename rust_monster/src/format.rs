@@ -0,0 +1,544 @@
+// Reading and writing problems.
+//
+// The text format has one entry per line. Everything after a `#` is a
+// comment and blank lines are ignored. The settings may come in any order,
+// but each must appear exactly once; the rules (`turn_order`,
+// `monster_damage`, `monster_target`, `monster_growth`, `boosts_per_turn`,
+// `health_boost`, `max_health_boosts` and `boost_caps`) may be left out to get
+// the defaults, where `boosts_per_turn` means no limit. `boost_caps` lists
+// one cap per hero, from the front, and may stop early. Heroes are listed in
+// the order they stand in, one `hero <health> <damage> [<speed>]` line each,
+// where the speed is 0 if left out. The damage may be a range such as `3-7`,
+// for damage left to chance:
+//
+//     # The monster from the original puzzle.
+//     monster_health 856867849
+//     chosen_hero 2
+//     boost_damage 1
+//     max_boosts 20
+//     turn_order monster_first
+//     monster_damage 1
+//     monster_target front
+//     monster_growth 0
+//     hero 29 1910
+//     hero 2112 195
+//     hero 43880 16
+//     hero 1 18586
+//
+// The JSON format is the serde representation of `Problem`, with the same
+// field names.
+
+use problem::{Hero, MonsterAttack, Problem, Rules};
+use serde_json;
+
+use std::cmp;
+use std::error::Error;
+use std::fmt;
+use std::io::Read;
+use std::str::FromStr;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+impl Format {
+    // JSON problems are objects, and no line of the text format starts with
+    // a brace.
+    pub fn detect(input: &str) -> Format {
+        if input.trim_start().starts_with('{') {
+            Format::Json
+        } else {
+            Format::Text
+        }
+    }
+
+    pub fn parse(self, input: &str) -> Result<Problem, ParseError> {
+        match self {
+            Format::Text => parse_text(input),
+            Format::Json => parse_json(input),
+        }
+    }
+
+    pub fn write(self, problem: &Problem) -> String {
+        match self {
+            Format::Text => write_text(problem),
+            Format::Json => write_json(problem),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown format: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseError {
+    // Not all errors can be pinned to a line, e.g. a missing setting.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl ParseError {
+    fn at(line: usize, message: String) -> ParseError {
+        ParseError {
+            line: Some(line),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+// Reads a problem in either format.
+pub fn read_problem<R: Read>(mut reader: R) -> Result<Problem, ParseError> {
+    let mut input = String::new();
+    reader.read_to_string(&mut input).map_err(|e| ParseError {
+        line: None,
+        message: e.to_string(),
+    })?;
+    parse_problem(&input)
+}
+
+pub fn parse_problem(input: &str) -> Result<Problem, ParseError> {
+    Format::detect(input).parse(input)
+}
+
+pub fn parse_text(input: &str) -> Result<Problem, ParseError> {
+    let mut monster_health = None;
+    let mut chosen_hero = None;
+    let mut boost_damage = None;
+    let mut max_boosts = None;
+    let mut turn_order = None;
+    let mut monster_damage = None;
+    let mut monster_target = None;
+    let mut monster_growth = None;
+    let mut boosts_per_turn = None;
+    let mut health_boost = None;
+    let mut max_health_boosts = None;
+    let mut boost_caps = None;
+    let mut heroes = Vec::new();
+
+    for (index, line) in input.lines().enumerate() {
+        let line_number = index + 1;
+        let content = match line.find('#') {
+            Some(comment) => &line[..comment],
+            None => line,
+        };
+        let mut words = content.split_whitespace();
+        let key = match words.next() {
+            Some(key) => key,
+            None => continue,
+        };
+        let args: Vec<&str> = words.collect();
+        let expect_args = |count: usize| {
+            if args.len() == count {
+                Ok(())
+            } else {
+                Err(ParseError::at(
+                    line_number,
+                    format!("{} takes {} values, got {}", key, count, args.len()),
+                ))
+            }
+        };
+        match key {
+            "hero" => {
+                if args.len() != 2 && args.len() != 3 {
+                    return Err(ParseError::at(
+                        line_number,
+                        format!("hero takes 2 or 3 values, got {}", args.len()),
+                    ));
+                }
+                // A range like `3-7`; a leading minus is a negative damage.
+                let (damage, max_damage) = match args[1].get(1..).and_then(|rest| rest.find('-')) {
+                    Some(at) => (&args[1][..=at], Some(&args[1][at + 2..])),
+                    None => (args[1], None),
+                };
+                heroes.push(Hero {
+                    health: parse_value(line_number, "hero health", args[0])?,
+                    damage: parse_value(line_number, "hero damage", damage)?,
+                    speed: match args.get(2) {
+                        Some(speed) => parse_value(line_number, "hero speed", speed)?,
+                        None => 0,
+                    },
+                    max_damage: match max_damage {
+                        Some(max) => Some(parse_value(line_number, "hero damage", max)?),
+                        None => None,
+                    },
+                });
+            }
+            "monster_health" => {
+                expect_args(1)?;
+                set_once(line_number, key, &mut monster_health, args[0])?;
+            }
+            "chosen_hero" => {
+                expect_args(1)?;
+                set_once(line_number, key, &mut chosen_hero, args[0])?;
+            }
+            "boost_damage" => {
+                expect_args(1)?;
+                set_once(line_number, key, &mut boost_damage, args[0])?;
+            }
+            "max_boosts" => {
+                expect_args(1)?;
+                set_once(line_number, key, &mut max_boosts, args[0])?;
+            }
+            "turn_order" => {
+                expect_args(1)?;
+                set_once(line_number, key, &mut turn_order, args[0])?;
+            }
+            "monster_damage" => {
+                expect_args(1)?;
+                set_once(line_number, key, &mut monster_damage, args[0])?;
+            }
+            "monster_target" => {
+                expect_args(1)?;
+                set_once(line_number, key, &mut monster_target, args[0])?;
+            }
+            "monster_growth" => {
+                expect_args(1)?;
+                set_once(line_number, key, &mut monster_growth, args[0])?;
+            }
+            "boosts_per_turn" => {
+                expect_args(1)?;
+                set_once(line_number, key, &mut boosts_per_turn, args[0])?;
+            }
+            "health_boost" => {
+                expect_args(1)?;
+                set_once(line_number, key, &mut health_boost, args[0])?;
+            }
+            "max_health_boosts" => {
+                expect_args(1)?;
+                set_once(line_number, key, &mut max_health_boosts, args[0])?;
+            }
+            "boost_caps" => {
+                if boost_caps.is_some() {
+                    return Err(ParseError::at(line_number, format!("{} is set twice", key)));
+                }
+                let caps = args
+                    .iter()
+                    .map(|word| parse_value(line_number, "boost cap", word))
+                    .collect::<Result<Vec<usize>, _>>()?;
+                boost_caps = Some(caps);
+            }
+            _ => {
+                return Err(ParseError::at(
+                    line_number,
+                    format!("unknown setting: {}", key),
+                ))
+            }
+        }
+    }
+
+    let default_attack = MonsterAttack::default();
+    Ok(Problem {
+        monster_health: required("monster_health", monster_health)?,
+        heroes,
+        chosen_hero: required("chosen_hero", chosen_hero)?,
+        boost_damage: required("boost_damage", boost_damage)?,
+        max_boosts: required("max_boosts", max_boosts)?,
+        rules: Rules {
+            turn_order: turn_order.unwrap_or_default(),
+            monster_attack: MonsterAttack {
+                damage: monster_damage.unwrap_or(default_attack.damage),
+                target: monster_target.unwrap_or(default_attack.target),
+                growth: monster_growth.unwrap_or(default_attack.growth),
+            },
+            boosts_per_turn,
+            health_boost: health_boost.unwrap_or(0),
+            max_health_boosts: max_health_boosts.unwrap_or(0),
+            boost_caps: boost_caps.unwrap_or_default(),
+        },
+    })
+}
+
+fn parse_value<T: FromStr>(line: usize, what: &str, word: &str) -> Result<T, ParseError>
+where
+    T::Err: fmt::Display,
+{
+    word.parse()
+        .map_err(|e| ParseError::at(line, format!("invalid {} {:?}: {}", what, word, e)))
+}
+
+fn set_once<T: FromStr>(
+    line: usize,
+    key: &str,
+    slot: &mut Option<T>,
+    word: &str,
+) -> Result<(), ParseError>
+where
+    T::Err: fmt::Display,
+{
+    if slot.is_some() {
+        return Err(ParseError::at(line, format!("{} is set twice", key)));
+    }
+    *slot = Some(parse_value(line, key, word)?);
+    Ok(())
+}
+
+fn required<T>(key: &str, value: Option<T>) -> Result<T, ParseError> {
+    value.ok_or_else(|| ParseError {
+        line: None,
+        message: format!("missing setting: {}", key),
+    })
+}
+
+pub fn write_text(problem: &Problem) -> String {
+    let mut out = format!(
+        "monster_health {}\nchosen_hero {}\nboost_damage {}\nmax_boosts {}\n\
+         turn_order {}\nmonster_damage {}\nmonster_target {}\nmonster_growth {}\n",
+        problem.monster_health,
+        problem.chosen_hero,
+        problem.boost_damage,
+        problem.max_boosts,
+        problem.rules.turn_order,
+        problem.rules.monster_attack.damage,
+        problem.rules.monster_attack.target,
+        problem.rules.monster_attack.growth
+    );
+    if let Some(limit) = problem.rules.boosts_per_turn {
+        out += &format!("boosts_per_turn {}\n", limit);
+    }
+    if problem.rules.health_boost != 0 {
+        out += &format!("health_boost {}\n", problem.rules.health_boost);
+    }
+    if problem.rules.max_health_boosts != 0 {
+        out += &format!("max_health_boosts {}\n", problem.rules.max_health_boosts);
+    }
+    if !problem.rules.boost_caps.is_empty() {
+        out += "boost_caps";
+        for cap in &problem.rules.boost_caps {
+            out += &format!(" {}", cap);
+        }
+        out += "\n";
+    }
+    for hero in &problem.heroes {
+        out += &format!("hero {} {}", hero.health, hero.damage);
+        if let Some(max_damage) = hero.max_damage {
+            out += &format!("-{}", max_damage);
+        }
+        if hero.speed != 0 {
+            out += &format!(" {}", hero.speed);
+        }
+        out += "\n";
+    }
+    out
+}
+
+pub fn parse_json(input: &str) -> Result<Problem, ParseError> {
+    serde_json::from_str(input).map_err(|e| ParseError {
+        line: Some(e.line()),
+        message: e.to_string(),
+    })
+}
+
+pub fn write_json(problem: &Problem) -> String {
+    // Serializing plain structs of numbers can't fail.
+    serde_json::to_string_pretty(problem).unwrap() + "\n"
+}
+
+// Lays out rows of cells as right-aligned columns, the first row being the
+// header.
+pub(crate) fn align_columns(rows: &[Vec<String>]) -> String {
+    let mut widths = vec![0; rows.first().map_or(0, Vec::len)];
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = cmp::max(*width, cell.len());
+        }
+    }
+    let mut out = String::new();
+    for row in rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| format!("{:>1$}", cell, width))
+            .collect();
+        out += cells.join("  ").trim_end();
+        out += "\n";
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use problem::{Target, TurnOrder};
+
+    fn problem() -> Problem {
+        Problem {
+            monster_health: 856867849,
+            heroes: vec![Hero::new(29, 1910), Hero::new(2112, 195)],
+            chosen_hero: 1,
+            boost_damage: 1,
+            max_boosts: 20,
+            rules: Rules::default(),
+        }
+    }
+
+    fn assert_error_on_line(line: usize, input: &str) {
+        match parse_text(input) {
+            Err(e) => assert_eq!(Some(line), e.line, "{}", e),
+            Ok(p) => panic!("Expected a parse error, got {:?}", p),
+        }
+    }
+
+    #[test]
+    fn text_round_trip() {
+        assert_eq!(Ok(problem()), parse_text(&write_text(&problem())));
+    }
+
+    #[test]
+    fn turn_order_setting() {
+        let mut p = problem();
+        p.rules.turn_order = TurnOrder::MonsterFirst;
+        assert_eq!(Ok(p.clone()), parse_text(&write_text(&p)));
+        assert_eq!(Ok(p.clone()), parse_json(&write_json(&p)));
+        assert_error_on_line(1, "turn_order monster_second\n");
+    }
+
+    #[test]
+    fn boosts_per_turn_setting() {
+        let mut p = problem();
+        p.rules.boosts_per_turn = Some(2);
+        assert_eq!(Ok(p.clone()), parse_text(&write_text(&p)));
+        assert_eq!(Ok(p.clone()), parse_json(&write_json(&p)));
+        assert!(!write_json(&problem()).contains("boosts_per_turn"));
+    }
+
+    #[test]
+    fn health_boosts_and_caps() {
+        let mut p = problem();
+        p.rules.health_boost = 4;
+        p.rules.max_health_boosts = 2;
+        p.rules.boost_caps = vec![1, 0];
+        assert_eq!(Ok(p.clone()), parse_text(&write_text(&p)));
+        assert_eq!(Ok(p.clone()), parse_json(&write_json(&p)));
+        assert!(!write_json(&problem()).contains("health_boost"));
+        assert!(!write_json(&problem()).contains("boost_caps"));
+    }
+
+    #[test]
+    fn hero_speed() {
+        let mut p = problem();
+        p.heroes[1].speed = 3;
+        assert!(write_text(&p).contains("hero 2112 195 3\n"));
+        assert_eq!(Ok(p.clone()), parse_text(&write_text(&p)));
+        assert_eq!(Ok(p.clone()), parse_json(&write_json(&p)));
+        assert!(!write_json(&problem()).contains("speed"));
+        assert_error_on_line(1, "hero 1 1 1 1\n");
+        assert_error_on_line(1, "hero 1 1 -1\n");
+    }
+
+    #[test]
+    fn damage_range() {
+        let mut p = problem();
+        p.heroes[0].max_damage = Some(1950);
+        p.heroes[1].speed = 2;
+        assert!(write_text(&p).contains("hero 29 1910-1950\n"));
+        assert!(write_text(&p).contains("hero 2112 195 2\n"));
+        assert_eq!(Ok(p.clone()), parse_text(&write_text(&p)));
+        assert_eq!(Ok(p.clone()), parse_json(&write_json(&p)));
+        assert!(!write_json(&problem()).contains("max_damage"));
+        assert_error_on_line(1, "hero 1 3-\n");
+        assert_error_on_line(1, "hero 1 3-x\n");
+    }
+
+    #[test]
+    fn monster_attack_settings() {
+        let mut p = problem();
+        p.rules.monster_attack = MonsterAttack {
+            damage: 3,
+            target: Target::All,
+            growth: 2,
+        };
+        assert_eq!(Ok(p.clone()), parse_text(&write_text(&p)));
+        assert_eq!(Ok(p.clone()), parse_json(&write_json(&p)));
+        assert_error_on_line(1, "monster_target back\n");
+        assert_error_on_line(1, "monster_damage -1\n");
+    }
+
+    #[test]
+    fn rules_default_when_missing() {
+        let json = "{\"monster_health\": 1, \"heroes\": [], \"chosen_hero\": 0, \
+                    \"boost_damage\": 1, \"max_boosts\": 0}";
+        assert_eq!(Rules::default(), parse_json(json).unwrap().rules);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        assert_eq!(Ok(problem()), parse_json(&write_json(&problem())));
+    }
+
+    #[test]
+    fn detect_format() {
+        assert_eq!(Format::Json, Format::detect("  \n{}"));
+        assert_eq!(Format::Text, Format::detect("# {\nhero 1 1"));
+        assert_eq!(Ok(problem()), parse_problem(&write_json(&problem())));
+    }
+
+    #[test]
+    fn comments_and_order() {
+        assert_eq!(
+            Ok(problem()),
+            parse_text(
+                "# The heroes come first.\n\
+                 hero 29 1910\n\
+                 \n\
+                 hero 2112 195 # Looks weak.\n\
+                 max_boosts 20\n\
+                 boost_damage 1\n\
+                 chosen_hero 1\n\
+                 monster_health 856867849\n"
+            )
+        );
+    }
+
+    #[test]
+    fn line_errors() {
+        assert_error_on_line(2, "monster_health 10\nhero 1\n");
+        assert_error_on_line(2, "monster_health 10\nhero -1 1\n");
+        assert_error_on_line(3, "monster_health 10\n\nmonster_health 11\n");
+        assert_error_on_line(1, "monster_health ten\n");
+        assert_error_on_line(1, "monster 10\n");
+    }
+
+    #[test]
+    fn missing_setting() {
+        let e = parse_text("monster_health 10\nchosen_hero 0\nboost_damage 1\n").unwrap_err();
+        assert_eq!(None, e.line);
+        assert_eq!("missing setting: max_boosts", e.message);
+    }
+
+    #[test]
+    fn shipped_problem() {
+        let problem = parse_text(include_str!("../problems/gist.txt")).unwrap();
+        assert_eq!(Ok(()), problem.validate());
+        assert_eq!(4, problem.heroes.len());
+    }
+
+    #[test]
+    fn json_error_line() {
+        assert_eq!(
+            Some(2),
+            parse_json("{\n\"monster_health\": true}").unwrap_err().line
+        );
+    }
+}
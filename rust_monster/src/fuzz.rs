@@ -0,0 +1,380 @@
+use backend::{solve_brute_force, MonsterSolver};
+use error::MonsterError;
+use generate::{Generator, GeneratorConfig};
+use problem::{Problem, Rules, Target, TurnOrder};
+use simulation::solution_is_valid_with_health;
+use solution::{solve_with, SolveOptions};
+
+use std::fmt;
+use std::mem;
+
+// A way in which a backend disagrees with the simulation.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Disagreement {
+    // The backend found nothing, but these boosts work.
+    MissedSolution(Vec<usize>),
+    // The backend's boosts don't work.
+    InvalidSolution(Vec<usize>),
+    // The backend failed outright.
+    Error(MonsterError),
+}
+
+impl fmt::Display for Disagreement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Disagreement::MissedSolution(ref boosts) => {
+                write!(f, "missed a solution: {:?}", boosts)
+            }
+            Disagreement::InvalidSolution(ref boosts) => {
+                write!(f, "gave an invalid solution: {:?}", boosts)
+            }
+            Disagreement::Error(ref e) => write!(f, "failed: {}", e),
+        }
+    }
+}
+
+// A generated problem the backend got wrong, shrunk as far as it would go.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Failure {
+    // Which of the generated problems it was.
+    pub case: usize,
+    pub original: Problem,
+    pub problem: Problem,
+    pub disagreement: Disagreement,
+    // Whether the shrunk problem can be solved at all.
+    pub solvable: bool,
+}
+
+impl Failure {
+    // The shrunk problem as a test case for tests/examples.rs.
+    pub fn example_test(&self, name: &str) -> String {
+        example_test(name, &self.problem, self.solvable)
+    }
+}
+
+// Checks the backend's answer against the simulation, in both directions. The
+// problem has to be small enough for the brute-force solver; errors from that
+// are returned as they are.
+pub fn check(
+    problem: &Problem,
+    solver: &dyn MonsterSolver,
+) -> Result<Option<Disagreement>, MonsterError> {
+    let options = SolveOptions::default();
+    let report = match solve_with(problem, solver, &options) {
+        Ok(report) => report,
+        Err(e) => return Ok(Some(Disagreement::Error(e))),
+    };
+    let health_boosts = report.health_boosts.unwrap_or_default();
+    Ok(match report.boosts {
+        Some(boosts) => match solution_is_valid_with_health(problem, &boosts, &health_boosts) {
+            Ok(true) => None,
+            Ok(false) | Err(_) => Some(Disagreement::InvalidSolution(boosts)),
+        },
+        None => solve_brute_force(problem, &options)?
+            .boosts
+            .map(Disagreement::MissedSolution),
+    })
+}
+
+// Runs `count` problems from the generator through `check`, and shrinks every
+// one the backend gets wrong.
+pub fn fuzz(
+    solver: &dyn MonsterSolver,
+    seed: u64,
+    count: usize,
+    config: GeneratorConfig,
+) -> Result<Vec<Failure>, MonsterError> {
+    let mut failures = Vec::new();
+    for (case, problem) in Generator::new(seed, config).take(count).enumerate() {
+        if let Some(disagreement) = check(&problem, solver)? {
+            let (shrunk, disagreement) = shrink(&problem, disagreement, solver)?;
+            failures.push(Failure {
+                case,
+                original: problem,
+                solvable: solve_brute_force(&shrunk, &SolveOptions::default())?
+                    .boosts
+                    .is_some(),
+                problem: shrunk,
+                disagreement,
+            });
+        }
+    }
+    Ok(failures)
+}
+
+// Repeatedly replaces the problem by a smaller one on which the backend still
+// disagrees in the same way, until none of the smaller ones do.
+pub fn shrink(
+    problem: &Problem,
+    disagreement: Disagreement,
+    solver: &dyn MonsterSolver,
+) -> Result<(Problem, Disagreement), MonsterError> {
+    let mut problem = problem.clone();
+    let mut disagreement = disagreement;
+    'outer: loop {
+        for candidate in smaller_problems(&problem) {
+            if candidate.validate().is_err() {
+                continue;
+            }
+            if let Some(found) = check(&candidate, solver)? {
+                if mem::discriminant(&found) == mem::discriminant(&disagreement) {
+                    problem = candidate;
+                    disagreement = found;
+                    continue 'outer;
+                }
+            }
+        }
+        return Ok((problem, disagreement));
+    }
+}
+
+// Every candidate is smaller than the problem in some respect and no larger
+// in any other, so shrinking always ends. The biggest steps come first.
+fn smaller_problems(problem: &Problem) -> Vec<Problem> {
+    let mut candidates = Vec::new();
+    let mut add = |change: &dyn Fn(&mut Problem)| {
+        let mut candidate = problem.clone();
+        change(&mut candidate);
+        candidates.push(candidate);
+    };
+
+    for i in 0..problem.heroes.len() {
+        if i != problem.chosen_hero {
+            add(&|p: &mut Problem| {
+                p.heroes.remove(i);
+                if i < p.chosen_hero {
+                    p.chosen_hero -= 1;
+                }
+            });
+        }
+    }
+
+    let rules = &problem.rules;
+    if *rules != Rules::default() {
+        add(&|p: &mut Problem| p.rules = Rules::default());
+    }
+    if rules.turn_order != TurnOrder::HeroesFirst {
+        add(&|p: &mut Problem| p.rules.turn_order = TurnOrder::HeroesFirst);
+    }
+    if rules.monster_attack.target != Target::Front {
+        add(&|p: &mut Problem| p.rules.monster_attack.target = Target::Front);
+    }
+    for growth in smaller(rules.monster_attack.growth as u64, 0) {
+        add(&|p: &mut Problem| p.rules.monster_attack.growth = growth as usize);
+    }
+    for damage in smaller(rules.monster_attack.damage as u64, 0) {
+        add(&|p: &mut Problem| p.rules.monster_attack.damage = damage as usize);
+    }
+
+    for max_boosts in smaller(problem.max_boosts as u64, 0) {
+        add(&|p: &mut Problem| p.max_boosts = max_boosts as usize);
+    }
+    for boost_damage in smaller(problem.boost_damage as u64, 0) {
+        add(&|p: &mut Problem| p.boost_damage = boost_damage as i64);
+    }
+    for health in smaller(problem.monster_health as u64, 1) {
+        add(&|p: &mut Problem| p.monster_health = health as i64);
+    }
+    for (i, hero) in problem.heroes.iter().enumerate() {
+        for health in smaller(hero.health as u64, 1) {
+            add(&|p: &mut Problem| p.heroes[i].health = health as usize);
+        }
+        for damage in smaller(hero.damage as u64, 0) {
+            add(&|p: &mut Problem| p.heroes[i].damage = damage as i64);
+        }
+        for speed in smaller(hero.speed as u64, 0) {
+            add(&|p: &mut Problem| p.heroes[i].speed = speed as usize);
+        }
+        if hero.max_damage.is_some() {
+            add(&|p: &mut Problem| p.heroes[i].max_damage = None);
+        }
+    }
+    candidates
+}
+
+// Values below `value` and no lower than `least`, smallest first.
+fn smaller(value: u64, least: u64) -> Vec<u64> {
+    let mut values: Vec<u64> = vec![least, value / 2, value.saturating_sub(1)]
+        .into_iter()
+        .filter(|&v| least <= v && v < value)
+        .collect();
+    values.dedup();
+    values
+}
+
+// Writes the problem as a test in the style of tests/examples.rs.
+pub fn example_test(name: &str, problem: &Problem, solvable: bool) -> String {
+    let mut out = String::new();
+    out += "#[test]\n";
+    out += &format!("fn {}() {{\n", name);
+    out += &format!(
+        "    {}(&Problem {{\n",
+        if solvable {
+            "assert_solveable"
+        } else {
+            "assert_not_solveable"
+        }
+    );
+    out += &format!("        monster_health: {},\n", problem.monster_health);
+    out += "        heroes: vec![\n";
+    // The tests build heroes with `Hero::new`, which leaves out the rest.
+    for hero in &problem.heroes {
+        let base = format!("Hero::new({}, {})", hero.health, hero.damage);
+        if hero.speed == 0 && hero.max_damage.is_none() {
+            out += &format!("            {},\n", base);
+            continue;
+        }
+        out += "            Hero {\n";
+        if hero.speed != 0 {
+            out += &format!("                speed: {},\n", hero.speed);
+        }
+        if let Some(max_damage) = hero.max_damage {
+            out += &format!("                max_damage: Some({}),\n", max_damage);
+        }
+        out += &format!("                ..{}\n", base);
+        out += "            },\n";
+    }
+    out += "        ],\n";
+    out += &format!("        chosen_hero: {},\n", problem.chosen_hero);
+    out += &format!("        boost_damage: {},\n", problem.boost_damage);
+    out += &format!("        max_boosts: {},\n", problem.max_boosts);
+    let rules = &problem.rules;
+    if *rules == Rules::default() {
+        out += "        rules: Rules::default(),\n";
+    } else {
+        let attack = &rules.monster_attack;
+        out += "        rules: Rules {\n";
+        out += &format!(
+            "            turn_order: TurnOrder::{:?},\n",
+            rules.turn_order
+        );
+        out += "            monster_attack: MonsterAttack {\n";
+        out += &format!("                damage: {},\n", attack.damage);
+        out += &format!("                target: Target::{:?},\n", attack.target);
+        out += &format!("                growth: {},\n", attack.growth);
+        out += "            },\n";
+        out += &format!(
+            "            boosts_per_turn: {:?},\n",
+            rules.boosts_per_turn
+        );
+        out += &format!("            health_boost: {},\n", rules.health_boost);
+        out += &format!(
+            "            max_health_boosts: {},\n",
+            rules.max_health_boosts
+        );
+        out += &format!("            boost_caps: vec!{:?},\n", rules.boost_caps);
+        out += "        },\n";
+    }
+    out += "    });\n";
+    out += "}\n";
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use backend::Backend;
+    use problem::test_problem as problem;
+    use problem::Hero;
+    use solution::SolveReport;
+
+    // Claims every problem is unsolvable.
+    struct Pessimist;
+
+    impl MonsterSolver for Pessimist {
+        fn name(&self) -> &'static str {
+            "pessimist"
+        }
+
+        fn solve(&self, _: &Problem, _: &SolveOptions) -> Result<SolveReport, MonsterError> {
+            Ok(SolveReport::infeasible())
+        }
+    }
+
+    #[test]
+    fn native_agrees() {
+        assert_eq!(Ok(None), check(&problem(), &Backend::Native));
+    }
+
+    #[test]
+    fn finds_missed_solution() {
+        assert_eq!(
+            Ok(Some(Disagreement::MissedSolution(vec![1]))),
+            check(&problem(), &Pessimist)
+        );
+    }
+
+    #[test]
+    fn shrinks_to_minimal() {
+        let (shrunk, disagreement) = shrink(
+            &problem(),
+            Disagreement::MissedSolution(vec![1]),
+            &Pessimist,
+        )
+        .unwrap();
+        // The chosen hero kills the monster on their own.
+        assert_eq!(
+            Problem {
+                monster_health: 1,
+                heroes: vec![Hero::new(1, 1)],
+                chosen_hero: 0,
+                boost_damage: 0,
+                max_boosts: 0,
+                rules: Rules::default(),
+            },
+            shrunk
+        );
+        assert_eq!(Disagreement::MissedSolution(vec![]), disagreement);
+    }
+
+    #[test]
+    fn various_smaller() {
+        assert_eq!(vec![0, 5, 9], smaller(10, 0));
+        assert_eq!(vec![1], smaller(2, 1));
+        assert!(smaller(1, 1).is_empty());
+    }
+
+    #[test]
+    fn writes_example_test() {
+        let mut problem = problem();
+        problem.heroes.truncate(1);
+        problem.chosen_hero = 0;
+        problem.rules.turn_order = TurnOrder::MonsterFirst;
+        assert_eq!(
+            "#[test]
+fn case_1() {
+    assert_not_solveable(&Problem {
+        monster_health: 10,
+        heroes: vec![
+            Hero::new(1, 1),
+        ],
+        chosen_hero: 0,
+        boost_damage: 2,
+        max_boosts: 2,
+        rules: Rules {
+            turn_order: TurnOrder::MonsterFirst,
+            monster_attack: MonsterAttack {
+                damage: 1,
+                target: Target::Front,
+                growth: 0,
+            },
+            boosts_per_turn: None,
+            health_boost: 0,
+            max_health_boosts: 0,
+            boost_caps: vec![],
+        },
+    });
+}
+",
+            example_test("case_1", &problem, false)
+        );
+        problem.heroes[0].speed = 2;
+        assert!(example_test("case_1", &problem, false).contains(
+            "            Hero {
+                speed: 2,
+                ..Hero::new(1, 1)
+            },
+"
+        ));
+    }
+}
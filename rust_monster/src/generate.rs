@@ -0,0 +1,158 @@
+use problem::{Hero, Problem, Rules};
+use rng::Rng;
+
+// Bounds for randomly generated problems. All the ranges are inclusive.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct GeneratorConfig {
+    pub min_heroes: usize,
+    pub max_heroes: usize,
+    pub max_health: usize,
+    pub max_damage: i64,
+    pub max_boost_damage: i64,
+    pub max_boosts: usize,
+    // Zero leaves every hero at the default speed.
+    pub max_speed: usize,
+    // Zero keeps the limit on boosts per turn from the rules; otherwise every
+    // problem gets a limit of its own, from 1 up to this.
+    pub max_boosts_per_turn: usize,
+    // Shared by all the generated problems.
+    pub rules: Rules,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> GeneratorConfig {
+        GeneratorConfig {
+            min_heroes: 1,
+            max_heroes: 6,
+            max_health: 5,
+            max_damage: 20,
+            max_boost_damage: 10,
+            max_boosts: 5,
+            max_speed: 0,
+            max_boosts_per_turn: 0,
+            rules: Rules::default(),
+        }
+    }
+}
+
+// An endless, reproducible stream of random problems.
+pub struct Generator {
+    rng: Rng,
+    config: GeneratorConfig,
+}
+
+impl Generator {
+    pub fn new(seed: u64, config: GeneratorConfig) -> Generator {
+        Generator {
+            rng: Rng::new(seed),
+            config,
+        }
+    }
+
+    pub fn problem(&mut self) -> Problem {
+        let config = &self.config;
+        let rng = &mut self.rng;
+        let hero_count = rng.between(config.min_heroes as u64, config.max_heroes as u64) as usize;
+        let heroes: Vec<Hero> = (0..hero_count)
+            .map(|_| {
+                let health = rng.between(1, config.max_health as u64) as usize;
+                let damage = rng.between(0, config.max_damage as u64) as i64;
+                // Drawing nothing keeps the problems for a seed as they were.
+                let speed = if config.max_speed > 0 {
+                    rng.between(0, config.max_speed as u64) as usize
+                } else {
+                    0
+                };
+                Hero {
+                    speed,
+                    ..Hero::new(health, damage)
+                }
+            })
+            .collect();
+        let boost_damage = rng.between(0, config.max_boost_damage as u64) as i64;
+        let max_boosts = rng.between(0, config.max_boosts as u64) as usize;
+
+        // Pick the monster's health so that it could die before the last hero
+        // does; anything tougher is trivially unsolvable.
+        let mut lifetime = 0;
+        let mut total_damage = 0;
+        for hero in &heroes {
+            lifetime = (hero.health as u64).saturating_add(lifetime);
+            total_damage = (hero.damage as u64)
+                .saturating_mul(lifetime)
+                .saturating_add(total_damage);
+        }
+        total_damage = (boost_damage as u64)
+            .saturating_mul(max_boosts as u64)
+            .saturating_mul(lifetime)
+            .saturating_add(total_damage);
+        let mut rules = config.rules.clone();
+        if config.max_boosts_per_turn > 0 {
+            rules.boosts_per_turn =
+                Some(rng.between(1, config.max_boosts_per_turn as u64) as usize);
+        }
+
+        Problem {
+            monster_health: rng.between(1, total_damage.clamp(1, i64::MAX as u64)) as i64,
+            chosen_hero: rng.between(0, hero_count as u64 - 1) as usize,
+            heroes,
+            boost_damage,
+            max_boosts,
+            rules,
+        }
+    }
+}
+
+impl Iterator for Generator {
+    type Item = Problem;
+
+    fn next(&mut self) -> Option<Problem> {
+        Some(self.problem())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reproducible() {
+        let a: Vec<Problem> = Generator::new(3, GeneratorConfig::default())
+            .take(20)
+            .collect();
+        let b: Vec<Problem> = Generator::new(3, GeneratorConfig::default())
+            .take(20)
+            .collect();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn respects_config() {
+        let config = GeneratorConfig {
+            min_heroes: 2,
+            max_heroes: 3,
+            max_health: 4,
+            max_damage: 5,
+            max_boost_damage: 6,
+            max_boosts: 7,
+            max_speed: 2,
+            max_boosts_per_turn: 3,
+            rules: Rules::default(),
+        };
+        for problem in Generator::new(11, config.clone()).take(200) {
+            assert_eq!(Ok(()), problem.validate());
+            assert!((2..=3).contains(&problem.heroes.len()));
+            assert!(problem.boost_damage <= 6);
+            assert!(problem.max_boosts <= 7);
+            assert!(problem
+                .rules
+                .boosts_per_turn
+                .is_some_and(|limit| (1..=3).contains(&limit)));
+            for hero in &problem.heroes {
+                assert!(hero.health <= 4);
+                assert!(hero.damage <= 5);
+                assert!(hero.speed <= 2);
+            }
+        }
+    }
+}
@@ -1,4 +1,20 @@
 extern crate lp_modeler;
+extern crate serde;
+extern crate serde_json;
+pub mod backend;
+pub mod batch;
+pub mod campaign;
+pub mod enumerate;
+pub mod error;
+pub mod explain;
+pub mod format;
+pub mod fuzz;
+pub mod generate;
+pub mod monte_carlo;
 pub mod problem;
+pub mod reachability;
+mod rng;
+pub mod schedule;
+pub mod sensitivity;
 pub mod simulation;
 pub mod solution;
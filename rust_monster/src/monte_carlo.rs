@@ -0,0 +1,271 @@
+// Estimates for problems where damage is left to chance.
+//
+// A hero with a `max_damage` deals anywhere from their `damage` to that on
+// each attack, so whether the chosen hero lands the killing blow is a matter
+// of probability. We estimate it by fighting many times with seeded random
+// damage, so the same seed always gives the same estimate, and give a 95%
+// confidence interval with it.
+
+use backend::next_multiset;
+use error::MonsterError;
+use problem::Problem;
+use rng::Rng;
+use serde::Serialize;
+use simulation::count_random_wins;
+use solution::attack_turns;
+
+// Every trial is fought turn by turn, so this bounds the turns simulated by
+// one call, over all trials and boost lists.
+pub const MAX_SIMULATED_TURNS: u128 = 100_000_000;
+// The most boost lists the optimiser tries.
+pub const MAX_CANDIDATES: u128 = 10_000;
+
+// The 97.5th percentile of the standard normal distribution.
+const Z_95: f64 = 1.959_963_984_540_054;
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize)]
+pub struct Estimate {
+    pub trials: u64,
+    pub wins: u64,
+    pub probability: f64,
+    // The Wilson score interval, which stays within [0, 1] and makes sense
+    // even when every trial is won or every trial lost.
+    pub low: f64,
+    pub high: f64,
+}
+
+impl Estimate {
+    fn new(trials: u64, wins: u64) -> Estimate {
+        let n = trials as f64;
+        let p = wins as f64 / n;
+        let z2 = Z_95 * Z_95;
+        let centre = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+        let margin = Z_95 / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+        Estimate {
+            trials,
+            wins,
+            probability: p,
+            // Rounding would otherwise leave the bounds just short of 0 or
+            // 1 when every trial goes the same way.
+            low: if wins == 0 { 0.0 } else { centre - margin },
+            high: if wins == trials { 1.0 } else { centre + margin },
+        }
+    }
+}
+
+// The estimated chance that the chosen hero lands the killing blow with the
+// given boosts.
+pub fn estimate_win_probability(
+    problem: &Problem,
+    boosts: &[usize],
+    trials: u64,
+    seed: u64,
+) -> Result<Estimate, MonsterError> {
+    check(problem, trials, 1)?;
+    let wins = count_random_wins(problem, boosts, trials, &mut Rng::new(seed))?;
+    Ok(Estimate::new(trials, wins))
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct BestBoosts {
+    pub boosts: Vec<usize>,
+    pub estimate: Estimate,
+    // How many boost lists were tried.
+    pub candidates: usize,
+}
+
+// Tries every boost list within the problem's limits and picks the one with
+// the best estimated chance, the one with the fewest boosts among equals.
+// Every list is tried on the same seed, so that they are compared on the same
+// luck. The best of many estimates tends to be lucky, though, so the estimate
+// reported for it comes from trials of its own.
+pub fn most_likely_boosts(
+    problem: &Problem,
+    trials: u64,
+    seed: u64,
+) -> Result<BestBoosts, MonsterError> {
+    // Without heroes the count below never grows, and would run through
+    // every possible number of boosts.
+    problem.validate()?;
+    let hero_count = problem.heroes.len();
+    // There are C(hero_count + max_boosts, max_boosts) lists before the
+    // caps, and each step of this is a binomial coefficient too.
+    let mut total: u128 = 1;
+    for i in 1..=problem.max_boosts as u128 {
+        total = total * (hero_count as u128 + i) / i;
+        if total > MAX_CANDIDATES {
+            return Err(MonsterError::TooLarge(format!(
+                "{} boosts for {} heroes can be handed out in more than {} ways",
+                problem.max_boosts, hero_count, MAX_CANDIDATES
+            )));
+        }
+    }
+    check(problem, trials, total + 1)?;
+
+    let mut best: Option<(u64, Vec<usize>)> = None;
+    let mut candidates = 0;
+    for size in 0..=problem.max_boosts {
+        let mut boosts = vec![0; size];
+        loop {
+            let mut counts = vec![0; hero_count];
+            for &hero in &boosts {
+                counts[hero] += 1;
+            }
+            if (0..hero_count).all(|hero| counts[hero] <= problem.boost_cap(hero)) {
+                candidates += 1;
+                let wins = count_random_wins(problem, &boosts, trials, &mut Rng::new(seed))?;
+                if best.as_ref().is_none_or(|&(most, _)| wins > most) {
+                    best = Some((wins, boosts.clone()));
+                }
+            }
+            if !next_multiset(&mut boosts, hero_count) {
+                break;
+            }
+        }
+    }
+
+    // No boosts at all always fits.
+    let (_, boosts) = best.expect("the empty boost list is always tried");
+    let estimate = estimate_win_probability(problem, &boosts, trials, !seed)?;
+    Ok(BestBoosts {
+        boosts,
+        estimate,
+        candidates,
+    })
+}
+
+// Checks that the problem can be estimated, with `runs` rounds of `trials`
+// trials each.
+fn check(problem: &Problem, trials: u64, runs: u128) -> Result<(), MonsterError> {
+    problem.validate()?;
+    problem.reject_health_boosts("the Monte Carlo estimate")?;
+    if trials == 0 {
+        return Err(MonsterError::InvalidProblem(
+            "the estimate needs at least one trial".to_string(),
+        ));
+    }
+    // A fight lasts until the last hero dies, at the latest.
+    let turns = attack_turns(&problem.heroes, &problem.rules)?
+        .into_iter()
+        .max()
+        .map_or(0, |turns| turns as u128 + 1);
+    let simulated = turns.saturating_mul(trials as u128).saturating_mul(runs);
+    if simulated > MAX_SIMULATED_TURNS {
+        return Err(MonsterError::TooLarge(format!(
+            "{} trials of up to {} turns each, over {} boost lists, are more than {} turns",
+            trials, turns, runs, MAX_SIMULATED_TURNS
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use problem::{Hero, Rules};
+
+    // The front hero deals 0 or 1 damage a turn, the chosen hero behind
+    // them 1.
+    fn problem() -> Problem {
+        Problem {
+            monster_health: 4,
+            heroes: vec![
+                Hero {
+                    max_damage: Some(1),
+                    ..Hero::new(10, 0)
+                },
+                Hero::new(10, 1),
+            ],
+            chosen_hero: 1,
+            boost_damage: 2,
+            max_boosts: 1,
+            rules: Rules::default(),
+        }
+    }
+
+    fn assert_contains(estimate: &Estimate, probability: f64) {
+        assert!(
+            estimate.low <= probability && probability <= estimate.high,
+            "{} not in {:?}",
+            probability,
+            estimate
+        );
+    }
+
+    #[test]
+    fn wilson_interval() {
+        let none = Estimate::new(10, 0);
+        assert_eq!(0.0, none.low);
+        assert!((none.high - 0.2775).abs() < 1e-4);
+        let all = Estimate::new(10, 10);
+        assert_eq!(1.0, all.high);
+        assert!((all.low - 0.7225).abs() < 1e-4);
+        let half = Estimate::new(100, 50);
+        assert!((half.low - 0.4038).abs() < 1e-4);
+        assert!((half.high - 0.5962).abs() < 1e-4);
+    }
+
+    #[test]
+    fn estimates_are_seeded() {
+        // The boosted chosen hero kills the monster unless the front hero
+        // does so first, by dealing 1 on both of the first two turns.
+        let estimate = estimate_win_probability(&problem(), &[1], 10000, 1).unwrap();
+        assert_contains(&estimate, 0.75);
+        assert!(estimate.high - estimate.low < 0.02);
+        assert_eq!(
+            Ok(estimate),
+            estimate_win_probability(&problem(), &[1], 10000, 1)
+        );
+        assert!(estimate_win_probability(&problem(), &[1], 10000, 2) != Ok(estimate));
+    }
+
+    #[test]
+    fn fixed_damage_is_certain() {
+        let mut problem = problem();
+        problem.heroes[0].max_damage = None;
+        let estimate = estimate_win_probability(&problem, &[], 100, 0).unwrap();
+        assert_eq!(100, estimate.wins);
+        assert_eq!(1.0, estimate.probability);
+    }
+
+    #[test]
+    fn picks_the_likeliest_boosts() {
+        let best = most_likely_boosts(&problem(), 10000, 3).unwrap();
+        assert_eq!(vec![1], best.boosts);
+        assert_eq!(3, best.candidates);
+        assert_contains(&best.estimate, 0.75);
+    }
+
+    #[test]
+    fn estimate_errors() {
+        let mut p = problem();
+        match estimate_win_probability(&p, &[], 0, 0) {
+            Err(MonsterError::InvalidProblem(_)) => {}
+            other => panic!("Expected an invalid problem, got {:?}", other),
+        }
+        match estimate_win_probability(&p, &[0, 0], 1, 0) {
+            Err(MonsterError::InvalidBoosts(_)) => {}
+            other => panic!("Expected invalid boosts, got {:?}", other),
+        }
+        p.heroes[1].health = 1 << 40;
+        match estimate_win_probability(&p, &[], 1000, 0) {
+            Err(MonsterError::TooLarge(_)) => {}
+            other => panic!("Expected too large, got {:?}", other),
+        }
+        let mut p = problem();
+        p.rules.health_boost = 1;
+        p.rules.max_health_boosts = 1;
+        match most_likely_boosts(&p, 10, 0) {
+            Err(MonsterError::Unsupported(_)) => {}
+            other => panic!("Expected unsupported, got {:?}", other),
+        }
+        let mut p = problem();
+        p.heroes.clear();
+        p.chosen_hero = 0;
+        p.max_boosts = usize::MAX;
+        match most_likely_boosts(&p, 10, 0) {
+            Err(MonsterError::InvalidProblem(_)) => {}
+            other => panic!("Expected an invalid problem, got {:?}", other),
+        }
+    }
+}
@@ -1,14 +1,401 @@
-#[derive(Debug, PartialEq, Eq, Clone)]
+use error::MonsterError;
+use serde::{Deserialize, Serialize};
+
+use std::cmp;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct Hero {
     pub health: usize,
-    pub damage: i32,
+    pub damage: i64,
+    // Within a turn, faster heroes attack first. Heroes equally fast attack
+    // in the order they stand in, so by default that is the order.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub speed: usize,
+    // With this set, each attack deals anywhere from `damage` to this much,
+    // all equally likely. Only the Monte Carlo estimates handle that.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_damage: Option<i64>,
+}
+
+impl Hero {
+    // A hero with the default speed and fixed damage.
+    pub fn new(health: usize, damage: i64) -> Hero {
+        Hero {
+            health,
+            damage,
+            speed: 0,
+            max_damage: None,
+        }
+    }
+}
+
+// The heroes by index, in the order they attack within a turn.
+pub fn attack_order(heroes: &[Hero]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..heroes.len()).collect();
+    order.sort_by_key(|&hero| cmp::Reverse(heroes[hero].speed));
+    order
+}
+
+// Who acts first within a turn. In the original puzzle the monster does, so
+// the front hero takes a hit before anyone attacks.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TurnOrder {
+    #[default]
+    HeroesFirst,
+    MonsterFirst,
+}
+
+impl fmt::Display for TurnOrder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            TurnOrder::HeroesFirst => "heroes_first",
+            TurnOrder::MonsterFirst => "monster_first",
+        })
+    }
+}
+
+impl FromStr for TurnOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<TurnOrder, String> {
+        match s {
+            "heroes_first" => Ok(TurnOrder::HeroesFirst),
+            "monster_first" => Ok(TurnOrder::MonsterFirst),
+            _ => Err(format!("unknown turn order: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    // Only the front-most living hero is hit.
+    #[default]
+    Front,
+    // Every living hero is hit.
+    All,
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Target::Front => "front",
+            Target::All => "all",
+        })
+    }
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Target, String> {
+        match s {
+            "front" => Ok(Target::Front),
+            "all" => Ok(Target::All),
+            _ => Err(format!("unknown target: {}", s)),
+        }
+    }
+}
+
+// The monster's attack: on turn `t` each target takes `damage + t * growth`.
+// Damage to one hero never spills over to the next.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MonsterAttack {
+    pub damage: usize,
+    pub target: Target,
+    pub growth: usize,
+}
+
+impl Default for MonsterAttack {
+    fn default() -> MonsterAttack {
+        MonsterAttack {
+            damage: 1,
+            target: Target::Front,
+            growth: 0,
+        }
+    }
+}
+
+impl MonsterAttack {
+    pub fn damage_on(&self, turn: usize) -> usize {
+        self.damage.saturating_add(self.growth.saturating_mul(turn))
+    }
+}
+
+// Variations on the rules of the game. The defaults are what the solver was
+// first written for.
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+pub struct Rules {
+    #[serde(default)]
+    pub turn_order: TurnOrder,
+    #[serde(default)]
+    pub monster_attack: MonsterAttack,
+    // How many boosts may be applied on any one turn, when they are scheduled
+    // rather than all applied before combat.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boosts_per_turn: Option<usize>,
+    // A second kind of boost, which adds this much health to a hero, with a
+    // budget of its own.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub health_boost: usize,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub max_health_boosts: usize,
+    // The most boosts of either kind each hero can take, by index. Heroes
+    // past the end of the list can take any number.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub boost_caps: Vec<usize>,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct Problem {
-    pub monster_health: i32,
+    pub monster_health: i64,
     pub heroes: Vec<Hero>,
     pub chosen_hero: usize,
-    pub boost_damage: i32,
+    pub boost_damage: i64,
     pub max_boosts: usize,
+    #[serde(default)]
+    pub rules: Rules,
+}
+
+impl Problem {
+    // The most boosts of either kind the hero can take.
+    pub fn boost_cap(&self, hero: usize) -> usize {
+        self.rules
+            .boost_caps
+            .get(hero)
+            .copied()
+            .unwrap_or(usize::MAX)
+    }
+
+    // Whether health boosts can make any difference.
+    pub fn has_health_boosts(&self) -> bool {
+        self.rules.health_boost > 0 && self.rules.max_health_boosts > 0
+    }
+
+    // For the methods that only know about damage boosts.
+    pub(crate) fn reject_health_boosts(&self, method: &str) -> Result<(), MonsterError> {
+        if self.has_health_boosts() {
+            return Err(MonsterError::Unsupported(format!(
+                "{} does not handle health boosts",
+                method
+            )));
+        }
+        Ok(())
+    }
+
+    // Whether any hero's damage is left to chance.
+    pub fn has_random_damage(&self) -> bool {
+        self.heroes.iter().any(|hero| hero.max_damage.is_some())
+    }
+
+    pub(crate) fn reject_random_damage(&self, method: &str) -> Result<(), MonsterError> {
+        if self.has_random_damage() {
+            return Err(MonsterError::Unsupported(format!(
+                "{} does not handle random damage",
+                method
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn reject_boost_caps(&self, method: &str) -> Result<(), MonsterError> {
+        if !self.rules.boost_caps.is_empty() {
+            return Err(MonsterError::Unsupported(format!(
+                "{} does not handle boost caps",
+                method
+            )));
+        }
+        Ok(())
+    }
+
+    // Checks the assumptions the simulation and the solvers make about the
+    // problem: there is a chosen hero, everyone starts alive, and damage never
+    // heals the monster.
+    pub fn validate(&self) -> Result<(), MonsterError> {
+        let invalid = |msg: String| Err(MonsterError::InvalidProblem(msg));
+        if self.monster_health <= 0 {
+            return invalid(format!(
+                "monster health {} is not positive",
+                self.monster_health
+            ));
+        }
+        if self.chosen_hero >= self.heroes.len() {
+            return invalid(format!(
+                "chosen hero {} does not exist, there are {} heroes",
+                self.chosen_hero,
+                self.heroes.len()
+            ));
+        }
+        let attack = &self.rules.monster_attack;
+        if attack.damage == 0 && attack.growth == 0 {
+            return invalid("the monster does no damage".to_string());
+        }
+        if self.boost_damage < 0 {
+            return invalid(format!("boost damage {} is negative", self.boost_damage));
+        }
+        if self.rules.boost_caps.len() > self.heroes.len() {
+            return invalid(format!(
+                "{} boost caps given for {} heroes",
+                self.rules.boost_caps.len(),
+                self.heroes.len()
+            ));
+        }
+        for (i, hero) in self.heroes.iter().enumerate() {
+            if hero.health == 0 {
+                return invalid(format!("hero {} has no health", i));
+            }
+            if hero.damage < 0 {
+                return invalid(format!("hero {} has negative damage {}", i, hero.damage));
+            }
+            if let Some(max_damage) = hero.max_damage {
+                if max_damage < hero.damage {
+                    return invalid(format!(
+                        "hero {} has damage range {}-{}",
+                        i, hero.damage, max_damage
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// The small problem the solver tests share: the chosen hero, behind, needs
+// one boost to land the killing blow on the third turn or two to land it on
+// the second.
+#[cfg(test)]
+pub(crate) fn test_problem() -> Problem {
+    Problem {
+        monster_health: 10,
+        heroes: vec![Hero::new(1, 1), Hero::new(3, 1)],
+        chosen_hero: 1,
+        boost_damage: 2,
+        max_boosts: 2,
+        rules: Rules::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problem() -> Problem {
+        Problem {
+            monster_health: 10,
+            heroes: vec![Hero::new(2, 4), Hero::new(1, 1)],
+            chosen_hero: 1,
+            boost_damage: 1,
+            max_boosts: 1,
+            rules: Rules::default(),
+        }
+    }
+
+    fn assert_invalid(problem: &Problem) {
+        match problem.validate() {
+            Err(MonsterError::InvalidProblem(_)) => {}
+            other => panic!("Expected an invalid problem, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn valid_problem() {
+        assert_eq!(Ok(()), problem().validate());
+    }
+
+    #[test]
+    fn missing_chosen_hero() {
+        let mut p = problem();
+        p.chosen_hero = 2;
+        assert_invalid(&p);
+        p.heroes.clear();
+        p.chosen_hero = 0;
+        assert_invalid(&p);
+    }
+
+    #[test]
+    fn dead_hero() {
+        let mut p = problem();
+        p.heroes[0].health = 0;
+        assert_invalid(&p);
+    }
+
+    #[test]
+    fn negative_damage() {
+        let mut p = problem();
+        p.heroes[1].damage = -1;
+        assert_invalid(&p);
+        let mut p = problem();
+        p.boost_damage = -1;
+        assert_invalid(&p);
+    }
+
+    #[test]
+    fn damage_range() {
+        let mut p = problem();
+        p.heroes[0].max_damage = Some(p.heroes[0].damage);
+        assert_eq!(Ok(()), p.validate());
+        assert!(p.has_random_damage());
+        p.heroes[0].max_damage = Some(p.heroes[0].damage - 1);
+        assert_invalid(&p);
+    }
+
+    #[test]
+    fn harmless_monster() {
+        let mut p = problem();
+        p.rules.monster_attack.damage = 0;
+        assert_invalid(&p);
+        p.rules.monster_attack.growth = 1;
+        assert_eq!(Ok(()), p.validate());
+    }
+
+    #[test]
+    fn attack_damage_grows() {
+        let attack = MonsterAttack {
+            damage: 2,
+            target: Target::All,
+            growth: 3,
+        };
+        assert_eq!(2, attack.damage_on(0));
+        assert_eq!(11, attack.damage_on(3));
+        assert_eq!(usize::MAX, attack.damage_on(usize::MAX));
+    }
+
+    #[test]
+    fn attack_order_by_speed() {
+        let heroes: Vec<Hero> = [0, 2, 1, 2]
+            .iter()
+            .map(|&speed| Hero {
+                speed,
+                ..Hero::new(1, 1)
+            })
+            .collect();
+        assert_eq!(vec![1, 3, 2, 0], attack_order(&heroes));
+        assert_eq!(vec![0, 1], attack_order(&problem().heroes));
+    }
+
+    #[test]
+    fn boost_caps() {
+        let mut p = problem();
+        assert_eq!(usize::MAX, p.boost_cap(0));
+        p.rules.boost_caps = vec![2];
+        assert_eq!(2, p.boost_cap(0));
+        assert_eq!(usize::MAX, p.boost_cap(1));
+        p.rules.boost_caps = vec![2, 0, 1];
+        assert_invalid(&p);
+    }
+
+    #[test]
+    fn dead_monster() {
+        let mut p = problem();
+        p.monster_health = 0;
+        assert_invalid(&p);
+    }
 }
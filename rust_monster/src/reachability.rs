@@ -0,0 +1,140 @@
+use backend::solve_combat;
+use error::MonsterError;
+use format::align_columns;
+use problem::Problem;
+use serde::Serialize;
+use solution::{attack_turns, combat_for, Objective};
+
+// Whether one hero can be made to land the killing blow, and if so with which
+// boosts and on which turn.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct KillingBlow {
+    pub hero: usize,
+    pub turn: Option<usize>,
+    pub boosts: Option<Vec<usize>>,
+}
+
+impl KillingBlow {
+    pub fn is_reachable(&self) -> bool {
+        self.boosts.is_some()
+    }
+}
+
+// For every hero in order, whether they can land the killing blow.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+#[serde(transparent)]
+pub struct ReachabilityMap {
+    pub heroes: Vec<KillingBlow>,
+}
+
+impl ReachabilityMap {
+    pub fn to_table(&self) -> String {
+        let mut rows = vec![vec![
+            "hero".to_string(),
+            "reachable".to_string(),
+            "turn".to_string(),
+            "boosts".to_string(),
+        ]];
+        for blow in &self.heroes {
+            let boosts = match blow.boosts {
+                Some(ref boosts) if boosts.is_empty() => "none".to_string(),
+                Some(ref boosts) => boosts
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(","),
+                None => "-".to_string(),
+            };
+            rows.push(vec![
+                blow.hero.to_string(),
+                if blow.is_reachable() { "yes" } else { "no" }.to_string(),
+                blow.turn.map_or("-".to_string(), |turn| turn.to_string()),
+                boosts,
+            ]);
+        }
+        align_columns(&rows)
+    }
+}
+
+// Solves the problem once for every hero as the chosen one, ignoring which
+// hero the problem itself picks. Who dies when is the same for all of them,
+// so that part of the combat is only worked out once.
+pub fn reachability(
+    problem: &Problem,
+    objective: Objective,
+) -> Result<ReachabilityMap, MonsterError> {
+    problem.validate()?;
+    problem.reject_health_boosts("the reachability map")?;
+    problem.reject_random_damage("the reachability map")?;
+    let attack_turns = attack_turns(&problem.heroes, &problem.rules)?;
+    let heroes = (0..problem.heroes.len())
+        .map(|hero| {
+            let combat = combat_for(problem, &attack_turns, hero)?;
+            let found = solve_combat(problem, &combat, objective)?;
+            Ok(KillingBlow {
+                hero,
+                turn: found.as_ref().map(|&(turn, _)| turn),
+                boosts: found.map(|(_, boosts)| boosts),
+            })
+        })
+        .collect::<Result<_, MonsterError>>()?;
+    Ok(ReachabilityMap { heroes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use backend::solve_brute_force;
+    use problem::{Hero, Rules};
+    use simulation::solution_is_valid;
+    use solution::SolveOptions;
+
+    fn problem() -> Problem {
+        Problem {
+            monster_health: 12,
+            heroes: vec![Hero::new(1, 1), Hero::new(3, 1), Hero::new(5, 3)],
+            chosen_hero: 1,
+            boost_damage: 2,
+            max_boosts: 2,
+            rules: Rules::default(),
+        }
+    }
+
+    #[test]
+    fn agrees_with_brute_force() {
+        let problem = problem();
+        let map = reachability(&problem, Objective::FewestBoosts).unwrap();
+        assert_eq!(3, map.heroes.len());
+        for blow in &map.heroes {
+            let mut chosen = problem.clone();
+            chosen.chosen_hero = blow.hero;
+            let options = SolveOptions {
+                objective: Objective::FewestBoosts,
+                ..SolveOptions::default()
+            };
+            let expected = solve_brute_force(&chosen, &options).unwrap();
+            assert_eq!(
+                expected.boosts.map(|boosts| boosts.len()),
+                blow.boosts.as_ref().map(Vec::len)
+            );
+            if let Some(ref boosts) = blow.boosts {
+                assert_eq!(Ok(true), solution_is_valid(&chosen, boosts));
+            }
+        }
+    }
+
+    #[test]
+    fn reachability_table() {
+        let map = reachability(&problem(), Objective::FewestBoosts).unwrap();
+        // The front hero dies after the first turn, and the middle one needs
+        // a boost on the front hero to keep the back one from finishing the
+        // monster.
+        assert_eq!(
+            "hero  reachable  turn  boosts\n   \
+                0         no     -       -\n   \
+                1        yes     2       0\n   \
+                2        yes     2    none\n",
+            map.to_table()
+        );
+    }
+}
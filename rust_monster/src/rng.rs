@@ -0,0 +1,76 @@
+// A small seeded generator (SplitMix64), so that generated problems are
+// reproducible across platforms and versions.
+#[derive(Debug, Clone)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [lo, hi]. Values from the incomplete block of `width` at the
+    // top of the range are drawn again, as they would favour the low end;
+    // for the small ranges of the generator that hardly ever happens, but
+    // damage ranges can be as wide as they like.
+    pub(crate) fn between(&mut self, lo: u64, hi: u64) -> u64 {
+        assert!(lo <= hi);
+        let width = match (hi - lo).checked_add(1) {
+            Some(width) => width,
+            None => return self.next_u64(),
+        };
+        let limit = u64::MAX - u64::MAX % width;
+        loop {
+            let value = self.next_u64();
+            if value < limit {
+                return lo + value % width;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_values() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        for _ in 0..10 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn between_bounds() {
+        let mut rng = Rng::new(7);
+        for _ in 0..1000 {
+            let x = rng.between(3, 5);
+            assert!((3..=5).contains(&x));
+        }
+        assert_eq!(4, rng.between(4, 4));
+        rng.between(0, u64::MAX);
+    }
+
+    #[test]
+    fn between_wide_range_is_uniform() {
+        // Taking the remainder alone would put half the values in the lowest
+        // third of this range.
+        let mut rng = Rng::new(9);
+        let third = 1u64 << 62;
+        let low = (0..10000)
+            .filter(|_| rng.between(0, 3 * third - 1) < third)
+            .count();
+        assert!((3000..3700).contains(&low), "{}", low);
+    }
+}
@@ -0,0 +1,217 @@
+use error::MonsterError;
+use problem::Problem;
+use serde::Serialize;
+use solution::{build_combat, ceil_div, Stage};
+
+use std::cmp;
+use std::convert::TryFrom;
+
+// Boosts applied during combat rather than before it, as (turn, hero) pairs
+// sorted by turn, with the turn on which the chosen hero lands the killing
+// blow.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct ScheduledSolution {
+    pub turn: usize,
+    pub schedule: Vec<(usize, usize)>,
+}
+
+// Finds a schedule that lets the chosen hero land the killing blow as early
+// as possible, with as few boosts as possible for that turn, keeping to the
+// problem's limit on boosts per turn if it has one.
+//
+// Only the chosen hero is ever boosted. A boost on anyone else on turn `t`
+// adds to their attacks from `t` up to the killing blow on turn `T`: at most
+// `T - t + 1` times, all before it. A boost on the chosen hero on the same
+// turn adds as much, `T - t` times before the killing blow and once with it,
+// so it does at least as well.
+pub fn solve_schedule(problem: &Problem) -> Result<Option<ScheduledSolution>, MonsterError> {
+    problem.validate()?;
+    problem.reject_health_boosts("the schedule solver")?;
+    problem.reject_random_damage("the schedule solver")?;
+    problem.reject_boost_caps("the schedule solver")?;
+    let combat = build_combat(problem)?;
+    for stage in combat.stages(problem, problem.max_boosts)? {
+        if let Some(turns) = boost_turns(&stage, problem) {
+            let schedule = turns
+                .into_iter()
+                .map(|turn| (turn, combat.chosen_hero))
+                .collect();
+            return Ok(Some(ScheduledSolution {
+                turn: stage.turn,
+                schedule,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+// The turns on which to boost the chosen hero for them to strike on the
+// stage's turn, using as few boosts as possible, in order.
+//
+// In units of the boost damage, a boost `x - 1` turns before the killing
+// blow adds `x - 1` before it and `x` in total. With `n` boosts, at most
+// `limit` of them for each `x` from 1 to `T + 1`, the total is anything from
+// packing them as late as possible to as early as possible, and what is
+// added before the killing blow is always the total less `n`. So we need the
+// fewest boosts whose total can reach what the killing blow needs, without
+// adding more before it than the monster can take.
+fn boost_turns(stage: &Stage, problem: &Problem) -> Option<Vec<usize>> {
+    let boost_damage = i128::from(problem.boost_damage);
+    if boost_damage == 0 {
+        return if stage.post_need <= 0 {
+            Some(Vec::new())
+        } else {
+            None
+        };
+    }
+    let max_pre = i128::from(stage.pre_limit) / boost_damage;
+    let needed = cmp::max(
+        0,
+        i128::from(ceil_div(stage.post_need, problem.boost_damage)),
+    );
+    let levels = stage.turn as i128 + 1;
+    let max_boosts = problem.max_boosts as i128;
+    let limit = problem
+        .rules
+        .boosts_per_turn
+        .map_or(cmp::max(max_boosts, 1), |limit| limit as i128);
+    if limit == 0 {
+        return if needed == 0 { Some(Vec::new()) } else { None };
+    }
+    let slots = limit.saturating_mul(levels);
+    let max_count = cmp::min(max_boosts, slots);
+
+    // Boosts packed as late as possible: `limit` of them with 1, then 2, and
+    // so on. Packed as early as possible they mirror that.
+    let lowest = |n: i128| {
+        let (full, rest) = (n / limit, n % limit);
+        limit
+            .saturating_mul(full.saturating_mul(full + 1) / 2)
+            .saturating_add(rest.saturating_mul(full + 1))
+    };
+    let highest = |n: i128| n.saturating_mul(levels + 1).saturating_sub(lowest(n));
+
+    if highest(max_count) < needed {
+        return None;
+    }
+    let (mut lo, mut hi) = (0, max_count);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if highest(mid) >= needed {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    // More boosts than that only add to the damage before the killing blow.
+    let count = cmp::max(lo, needed - max_pre);
+    if count > max_count || lowest(count) - count > max_pre {
+        return None;
+    }
+
+    // Starting from the latest packing, move boosts to the earliest free
+    // turns, last one first, until the total is just enough. The last one
+    // moved may only need to go part of the way.
+    let level = |slot: i128| slot / limit + 1;
+    let mut missing = cmp::max(needed, lowest(count)) - lowest(count);
+    let mut turns = Vec::with_capacity(usize::try_from(count).ok()?);
+    for i in (0..count).rev() {
+        let from = level(i);
+        let to = level(slots - count + i);
+        let x = if missing >= to - from {
+            missing -= to - from;
+            to
+        } else {
+            let x = from + missing;
+            missing = 0;
+            x
+        };
+        turns.push(usize::try_from(levels - x).ok()?);
+    }
+    turns.sort_unstable();
+    Some(turns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use problem::{Hero, Rules};
+    use simulation::schedule_is_valid;
+
+    fn problem(boosts_per_turn: Option<usize>) -> Problem {
+        Problem {
+            monster_health: 20,
+            heroes: vec![Hero::new(5, 1), Hero::new(10, 1)],
+            chosen_hero: 1,
+            boost_damage: 3,
+            max_boosts: 4,
+            rules: Rules {
+                boosts_per_turn,
+                ..Rules::default()
+            },
+        }
+    }
+
+    #[test]
+    fn boosts_without_a_limit() {
+        // Every boost on the first turn lets the chosen hero deal 10 damage
+        // on each of the first two.
+        let problem = problem(None);
+        let solution = solve_schedule(&problem).unwrap().unwrap();
+        assert_eq!(
+            ScheduledSolution {
+                turn: 1,
+                schedule: vec![(0, 1), (0, 1), (0, 1)],
+            },
+            solution
+        );
+        assert_eq!(Ok(true), schedule_is_valid(&problem, &solution.schedule));
+    }
+
+    #[test]
+    fn boosts_spread_over_turns() {
+        let problem = problem(Some(1));
+        let solution = solve_schedule(&problem).unwrap().unwrap();
+        assert_eq!(
+            ScheduledSolution {
+                turn: 2,
+                schedule: vec![(0, 1), (1, 1)],
+            },
+            solution
+        );
+        assert_eq!(Ok(true), schedule_is_valid(&problem, &solution.schedule));
+    }
+
+    #[test]
+    fn late_boosts_keep_the_front_hero_back() {
+        // Any boost on the first turn lets the front hero finish the monster
+        // on the second.
+        let problem = Problem {
+            monster_health: 7,
+            heroes: vec![Hero::new(5, 2), Hero::new(5, 1)],
+            chosen_hero: 1,
+            boost_damage: 2,
+            max_boosts: 1,
+            rules: Rules::default(),
+        };
+        assert_eq!(
+            Ok(Some(ScheduledSolution {
+                turn: 1,
+                schedule: vec![(1, 1)],
+            })),
+            solve_schedule(&problem)
+        );
+    }
+
+    #[test]
+    fn no_boosts_allowed() {
+        // The chosen hero outlives the front one and wins without help.
+        assert_eq!(
+            Ok(Some(ScheduledSolution {
+                turn: 14,
+                schedule: vec![],
+            })),
+            solve_schedule(&problem(Some(0)))
+        );
+    }
+}
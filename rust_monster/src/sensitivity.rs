@@ -0,0 +1,225 @@
+use backend::NativeBackend;
+use error::{MonsterError, OrOverflow};
+use problem::Problem;
+use solution::{build_combat, ceil_div, solve_with, Objective, SolveOptions};
+
+use std::convert::TryFrom;
+
+// `min_boost_damage` tries every boost damage in turn, and gives up past this.
+pub const MAX_BOOST_DAMAGE_SCAN: i64 = 1_000_000;
+
+// The smallest `max_boosts` with which the chosen hero can land the killing
+// blow, keeping everything else as it is, or None if no number will do.
+pub fn min_max_boosts(problem: &Problem) -> Result<Option<usize>, MonsterError> {
+    problem.validate()?;
+    problem.reject_health_boosts("sensitivity analysis")?;
+    problem.reject_random_damage("sensitivity analysis")?;
+    problem.reject_boost_caps("sensitivity analysis")?;
+    if problem.boost_damage == 0 {
+        return fewest_boosts(problem, 0);
+    }
+    // Leaving out boosts that change nothing, every boost in a solution with
+    // the fewest boosts adds at least one boost damage before the killing
+    // blow, which must stay below the monster's health. The only exception
+    // is the chosen hero's boosts on the first turn, and they never need more
+    // than the monster's health either. Anything allowed past this is never
+    // used.
+    let enough = ceil_div(problem.monster_health, problem.boost_damage)
+        .checked_mul(2)
+        .and_then(|boosts| usize::try_from(boosts).ok())
+        .or_overflow("the number of boosts that always suffices")?;
+    fewest_boosts(problem, enough)
+}
+
+// The smallest `boost_damage` with which the chosen hero can land the killing
+// blow, keeping everything else as it is, or None if no damage will do.
+//
+// More damage is not always better: it can just as well make the heroes in
+// front kill the monster. So there is nothing to bisect, and we try every
+// damage in turn. A single boost on the chosen hero that finishes the monster
+// on the first turn always works, which bounds the search.
+pub fn min_boost_damage(problem: &Problem) -> Result<Option<i64>, MonsterError> {
+    problem.validate()?;
+    problem.reject_health_boosts("sensitivity analysis")?;
+    problem.reject_random_damage("sensitivity analysis")?;
+    problem.reject_boost_caps("sensitivity analysis")?;
+    let combat = build_combat(problem)?;
+    // The chosen hero attacks on the first turn unless they die first. A
+    // hero who dies before attacking at all leaves an empty phase.
+    let first_phase = match combat.phases.iter().find(|phase| phase.end_stage > 0) {
+        Some(phase) => phase,
+        None => return Ok(None),
+    };
+    let health = problem.monster_health - first_phase.early_damage;
+    if health <= 0 {
+        return Ok(None);
+    }
+    // Without boosts the damage makes no difference.
+    let always_enough = if problem.max_boosts == 0 {
+        0
+    } else {
+        (health - combat.chosen_damage).max(0)
+    };
+
+    for boost_damage in 0..=always_enough {
+        if boost_damage > MAX_BOOST_DAMAGE_SCAN {
+            return Err(MonsterError::TooLarge(format!(
+                "no boost damage up to {} works",
+                MAX_BOOST_DAMAGE_SCAN
+            )));
+        }
+        let mut candidate = problem.clone();
+        candidate.boost_damage = boost_damage;
+        if is_feasible(&candidate)? {
+            return Ok(Some(boost_damage));
+        }
+    }
+    Ok(None)
+}
+
+// Which combinations of `max_boosts` and `boost_damage`, from zero up to the
+// given limits, let the chosen hero land the killing blow.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FeasibleRegion {
+    pub max_boosts: usize,
+    pub max_boost_damage: i64,
+    // For each boost damage, the fewest boosts that work, if any do within
+    // `max_boosts`.
+    fewest_boosts: Vec<Option<usize>>,
+}
+
+impl FeasibleRegion {
+    pub fn is_feasible(&self, max_boosts: usize, boost_damage: i64) -> bool {
+        usize::try_from(boost_damage)
+            .ok()
+            .and_then(|i| self.fewest_boosts.get(i))
+            .and_then(|&fewest| fewest)
+            .is_some_and(|fewest| fewest <= max_boosts)
+    }
+
+    // One row per `max_boosts` and one column per `boost_damage`, with 1 for
+    // the combinations that work and 0 for the rest.
+    pub fn to_csv(&self) -> String {
+        let mut out = "max_boosts\\boost_damage".to_string();
+        for boost_damage in 0..=self.max_boost_damage {
+            out += &format!(",{}", boost_damage);
+        }
+        out += "\n";
+        for max_boosts in 0..=self.max_boosts {
+            out += &max_boosts.to_string();
+            for boost_damage in 0..=self.max_boost_damage {
+                let cell = if self.is_feasible(max_boosts, boost_damage) {
+                    ",1"
+                } else {
+                    ",0"
+                };
+                out += cell;
+            }
+            out += "\n";
+        }
+        out
+    }
+}
+
+// With the boost damage fixed, anything that works with some number of boosts
+// still works when more are allowed, so a single solve per boost damage gives
+// a whole column.
+pub fn feasible_region(
+    problem: &Problem,
+    max_boosts: usize,
+    max_boost_damage: i64,
+) -> Result<FeasibleRegion, MonsterError> {
+    problem.validate()?;
+    problem.reject_health_boosts("sensitivity analysis")?;
+    problem.reject_random_damage("sensitivity analysis")?;
+    problem.reject_boost_caps("sensitivity analysis")?;
+    if max_boost_damage < 0 {
+        return Err(MonsterError::InvalidProblem(format!(
+            "boost damage {} is negative",
+            max_boost_damage
+        )));
+    }
+    let fewest_boosts = (0..=max_boost_damage)
+        .map(|boost_damage| {
+            let mut candidate = problem.clone();
+            candidate.boost_damage = boost_damage;
+            fewest_boosts(&candidate, max_boosts)
+        })
+        .collect::<Result<_, MonsterError>>()?;
+    Ok(FeasibleRegion {
+        max_boosts,
+        max_boost_damage,
+        fewest_boosts,
+    })
+}
+
+fn fewest_boosts(problem: &Problem, max_boosts: usize) -> Result<Option<usize>, MonsterError> {
+    let mut candidate = problem.clone();
+    candidate.max_boosts = max_boosts;
+    let options = SolveOptions {
+        objective: Objective::FewestBoosts,
+        ..SolveOptions::default()
+    };
+    Ok(solve_with(&candidate, &NativeBackend, &options)?
+        .boosts
+        .map(|boosts| boosts.len()))
+}
+
+fn is_feasible(problem: &Problem) -> Result<bool, MonsterError> {
+    Ok(
+        solve_with(problem, &NativeBackend, &SolveOptions::default())?
+            .boosts
+            .is_some(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use problem::test_problem as problem;
+    use problem::{Hero, Rules};
+
+    #[test]
+    fn minimal_parameters() {
+        assert_eq!(Ok(Some(1)), min_max_boosts(&problem()));
+        assert_eq!(Ok(Some(1)), min_boost_damage(&problem()));
+    }
+
+    #[test]
+    fn no_parameters_help() {
+        // The front hero kills the monster on the first turn.
+        let mut problem = problem();
+        problem.heroes[0].damage = 10;
+        assert_eq!(Ok(None), min_max_boosts(&problem));
+        assert_eq!(Ok(None), min_boost_damage(&problem));
+    }
+
+    #[test]
+    fn region_with_gaps() {
+        // With one boost on the chosen hero, 1 damage lands the blow on the
+        // fourth turn and 4 to 6 on the second. Anything else leaves the
+        // monster at most 3 health for the front hero on some turn.
+        let problem = Problem {
+            monster_health: 13,
+            heroes: vec![Hero::new(3, 3), Hero::new(3, 0)],
+            chosen_hero: 1,
+            boost_damage: 1,
+            max_boosts: 1,
+            rules: Rules::default(),
+        };
+        let region = feasible_region(&problem, 1, 8).unwrap();
+        assert_eq!(
+            "max_boosts\\boost_damage,0,1,2,3,4,5,6,7,8\n\
+             0,0,0,0,0,0,0,0,0,0\n\
+             1,0,1,0,0,1,1,1,0,0\n",
+            region.to_csv()
+        );
+        assert!(!region.is_feasible(1, 9));
+
+        // Two boosts of 2 on the chosen hero are as good as one of 4.
+        let mut weaker = problem.clone();
+        weaker.boost_damage = 2;
+        assert_eq!(Ok(Some(2)), min_max_boosts(&weaker));
+        assert_eq!(Ok(Some(1)), min_boost_damage(&weaker));
+    }
+}
@@ -1,119 +1,579 @@
-use problem::{Hero, Problem};
+use error::MonsterError;
+use format::align_columns;
+use problem::{attack_order, Hero, MonsterAttack, Problem, Rules, Target, TurnOrder};
+use rng::Rng;
+use serde::Serialize;
+use serde_json;
+use solution::attack_turns;
+
+use std::cmp;
+use std::collections::BTreeMap;
 
 #[derive(Debug, PartialEq, Eq)]
 struct Combat {
-    pub monster_health: i32,
+    pub monster_health: i64,
     pub heroes: Vec<Hero>,
+    pub rules: Rules,
+    // Damage added to a hero from some turn on, as (turn, hero, damage),
+    // sorted by turn.
+    pub late_boosts: Vec<(usize, usize, i64)>,
 }
 
-pub fn solution_is_valid(problem: &Problem, boosts: &[usize]) -> bool {
-    assert!(problem.chosen_hero < problem.heroes.len());
-    match find_hero_with_killing_blow(&apply_boosts(problem, boosts)) {
-        Some(i) => problem.chosen_hero == i,
-        None => false,
-    }
+pub fn solution_is_valid(problem: &Problem, boosts: &[usize]) -> Result<bool, MonsterError> {
+    solution_is_valid_with_health(problem, boosts, &[])
+}
+
+// Like `solution_is_valid`, with health boosts as well as damage boosts, each
+// given as the heroes they go to.
+pub fn solution_is_valid_with_health(
+    problem: &Problem,
+    boosts: &[usize],
+    health_boosts: &[usize],
+) -> Result<bool, MonsterError> {
+    problem.validate()?;
+    problem.reject_random_damage("the simulation")?;
+    Ok(
+        match find_hero_with_killing_blow(&apply_boosts(problem, boosts, health_boosts)?)? {
+            Some(i) => problem.chosen_hero == i,
+            None => false,
+        },
+    )
 }
 
-fn apply_boosts(problem: &Problem, boosts: &[usize]) -> Combat {
-    assert!(boosts.len() <= problem.max_boosts);
+fn apply_boosts(
+    problem: &Problem,
+    boosts: &[usize],
+    health_boosts: &[usize],
+) -> Result<Combat, MonsterError> {
+    let invalid = |msg: String| Err(MonsterError::InvalidBoosts(msg));
+    if boosts.len() > problem.max_boosts {
+        return invalid(format!(
+            "{} boosts given, at most {} allowed",
+            boosts.len(),
+            problem.max_boosts
+        ));
+    }
+    if health_boosts.len() > problem.rules.max_health_boosts {
+        return invalid(format!(
+            "{} health boosts given, at most {} allowed",
+            health_boosts.len(),
+            problem.rules.max_health_boosts
+        ));
+    }
     let mut heroes = problem.heroes.clone();
-    for boost in boosts {
-        heroes[*boost].damage += problem.boost_damage;
+    let mut counts = vec![0; heroes.len()];
+    for &boost in boosts.iter().chain(health_boosts) {
+        match counts.get_mut(boost) {
+            None => return invalid(format!("boost for hero {} who does not exist", boost)),
+            Some(count) => *count += 1,
+        }
     }
-    Combat {
+    for (i, &count) in counts.iter().enumerate() {
+        if count > problem.boost_cap(i) {
+            return invalid(format!(
+                "{} boosts for hero {}, at most {} allowed",
+                count,
+                i,
+                problem.boost_cap(i)
+            ));
+        }
+    }
+    for &boost in boosts {
+        let hero = &mut heroes[boost];
+        let overflow = || MonsterError::Overflow(format!("damage of hero {}", boost));
+        hero.damage = hero
+            .damage
+            .checked_add(problem.boost_damage)
+            .ok_or_else(overflow)?;
+        if let Some(max_damage) = hero.max_damage {
+            hero.max_damage = Some(
+                max_damage
+                    .checked_add(problem.boost_damage)
+                    .ok_or_else(overflow)?,
+            );
+        }
+    }
+    for &boost in health_boosts {
+        let hero = &mut heroes[boost];
+        hero.health = hero
+            .health
+            .checked_add(problem.rules.health_boost)
+            .ok_or_else(|| MonsterError::Overflow(format!("health of hero {}", boost)))?;
+    }
+    Ok(Combat {
         monster_health: problem.monster_health,
         heroes,
+        rules: problem.rules.clone(),
+        late_boosts: Vec::new(),
+    })
+}
+
+// Checks that a schedule of (turn, hero) boosts can be carried out: no more
+// boosts than allowed, in total or on any one turn, and each on a hero who
+// still attacks on that turn.
+pub fn validate_schedule(
+    problem: &Problem,
+    schedule: &[(usize, usize)],
+) -> Result<(), MonsterError> {
+    problem.validate()?;
+    problem.reject_random_damage("the simulation")?;
+    let invalid = |msg: String| Err(MonsterError::InvalidBoosts(msg));
+    if schedule.len() > problem.max_boosts {
+        return invalid(format!(
+            "{} boosts given, at most {} allowed",
+            schedule.len(),
+            problem.max_boosts
+        ));
     }
+    let attack_turns = attack_turns(&problem.heroes, &problem.rules)?;
+    let mut per_turn = BTreeMap::new();
+    for &(turn, hero) in schedule {
+        match attack_turns.get(hero) {
+            None => return invalid(format!("boost for hero {} who does not exist", hero)),
+            Some(&turns) if turns <= turn => {
+                return invalid(format!(
+                    "boost for hero {} on turn {}, when they no longer attack",
+                    hero, turn
+                ))
+            }
+            Some(_) => {}
+        }
+        let count = per_turn.entry(turn).or_insert(0);
+        *count += 1;
+        if let Some(limit) = problem.rules.boosts_per_turn {
+            if *count > limit {
+                return invalid(format!(
+                    "{} boosts on turn {}, at most {} allowed",
+                    count, turn, limit
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn apply_schedule(problem: &Problem, schedule: &[(usize, usize)]) -> Result<Combat, MonsterError> {
+    validate_schedule(problem, schedule)?;
+    let mut late_boosts: Vec<(usize, usize, i64)> = schedule
+        .iter()
+        .map(|&(turn, hero)| (turn, hero, problem.boost_damage))
+        .collect();
+    late_boosts.sort();
+    // Checking the damage with every boost applied keeps the fights in range.
+    let heroes: Vec<usize> = schedule.iter().map(|&(_, hero)| hero).collect();
+    apply_boosts(problem, &heroes, &[])?;
+    Ok(Combat {
+        monster_health: problem.monster_health,
+        heroes: problem.heroes.clone(),
+        rules: problem.rules.clone(),
+        late_boosts,
+    })
 }
 
-fn find_hero_with_killing_blow(combat: &Combat) -> Option<usize> {
-    if combat.heroes.is_empty() {
-        return None;
+// Whether the chosen hero lands the killing blow with the boosts applied as
+// scheduled. A schedule that can't be carried out is an error.
+pub fn schedule_is_valid(
+    problem: &Problem,
+    schedule: &[(usize, usize)],
+) -> Result<bool, MonsterError> {
+    Ok(simulate_schedule(problem, schedule)?.killer == Some(problem.chosen_hero))
+}
+
+pub fn simulate_schedule(
+    problem: &Problem,
+    schedule: &[(usize, usize)],
+) -> Result<Outcome, MonsterError> {
+    fight_by_phases(&apply_schedule(problem, schedule)?)
+}
+
+// How a combat ends. `turn` is the turn with the killing blow or, if the
+// monster survives, the turn on which the last hero dies.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct Outcome {
+    pub killer: Option<usize>,
+    pub turn: usize,
+    pub monster_health: i64,
+}
+
+// Jumps from one hero's death to the next rather than going turn by turn, so
+// the cost doesn't depend on how long the combat lasts.
+pub fn simulate(problem: &Problem, boosts: &[usize]) -> Result<Outcome, MonsterError> {
+    simulate_with_health(problem, boosts, &[])
+}
+
+pub fn simulate_with_health(
+    problem: &Problem,
+    boosts: &[usize],
+    health_boosts: &[usize],
+) -> Result<Outcome, MonsterError> {
+    problem.validate()?;
+    problem.reject_random_damage("the simulation")?;
+    fight_by_phases(&apply_boosts(problem, boosts, health_boosts)?)
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct Attack {
+    pub hero: usize,
+    pub damage: i64,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct TurnRecord {
+    pub turn: usize,
+    // Every hero still alive attacks, fastest first.
+    pub attacks: Vec<Attack>,
+    // What is left of the monster after this turn's attacks.
+    pub monster_health: i64,
+    // The heroes killed by the monster's attack this turn.
+    pub deaths: Vec<usize>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct BattleTrace {
+    pub hero_count: usize,
+    pub turns: Vec<TurnRecord>,
+    pub outcome: Outcome,
+}
+
+impl BattleTrace {
+    pub fn to_json(&self) -> String {
+        // Serializing plain structs of numbers can't fail.
+        serde_json::to_string_pretty(self).unwrap() + "\n"
+    }
+
+    // One row per turn and one column per hero, with the damage each hero
+    // dealt; dead heroes are shown as `-`.
+    pub fn to_table(&self) -> String {
+        let mut header = vec!["turn".to_string()];
+        header.extend((0..self.hero_count).map(|i| format!("hero {}", i)));
+        header.push("monster".to_string());
+        header.push("deaths".to_string());
+
+        let mut rows = vec![header];
+        for record in &self.turns {
+            let mut row = vec![record.turn.to_string()];
+            let mut damage = vec!["-".to_string(); self.hero_count];
+            for attack in &record.attacks {
+                damage[attack.hero] = attack.damage.to_string();
+            }
+            row.extend(damage);
+            row.push(record.monster_health.to_string());
+            let deaths: Vec<String> = record
+                .deaths
+                .iter()
+                .map(|hero| format!("hero {}", hero))
+                .collect();
+            row.push(deaths.join(", "));
+            rows.push(row);
+        }
+
+        align_columns(&rows)
     }
+}
+
+// Like `simulate`, but records every turn. Long combats make for long traces:
+// there is one record per turn.
+pub fn simulate_trace(problem: &Problem, boosts: &[usize]) -> Result<BattleTrace, MonsterError> {
+    simulate_trace_with_health(problem, boosts, &[])
+}
+
+pub fn simulate_trace_with_health(
+    problem: &Problem,
+    boosts: &[usize],
+    health_boosts: &[usize],
+) -> Result<BattleTrace, MonsterError> {
+    problem.validate()?;
+    problem.reject_random_damage("the simulation")?;
+    let mut turns = Vec::new();
+    let outcome = fight(
+        &apply_boosts(problem, boosts, health_boosts)?,
+        Some(&mut turns),
+    );
+    Ok(BattleTrace {
+        hero_count: problem.heroes.len(),
+        turns,
+        outcome,
+    })
+}
+
+fn find_hero_with_killing_blow(combat: &Combat) -> Result<Option<usize>, MonsterError> {
+    Ok(fight_by_phases(combat)?.killer)
+}
+
+// The same as `fight`, but using the number of turns each hero attacks for,
+// which doesn't depend on their damage. Between two deaths the monster takes
+// the same damage every turn, so we can find the turn of the killing blow
+// directly and only walk through the heroes on that turn. The damage is
+// summed as i128, which holds any number of i64 damages we could ever have.
+fn fight_by_phases(combat: &Combat) -> Result<Outcome, MonsterError> {
+    let attack_turns = attack_turns(&combat.heroes, &combat.rules)?;
+    let mut by_death: Vec<usize> = (0..combat.heroes.len()).collect();
+    by_death.sort_by_key(|&i| attack_turns[i]);
+    let mut damage: Vec<i128> = combat
+        .heroes
+        .iter()
+        .map(|hero| i128::from(hero.damage))
+        .collect();
+
+    let mut health = i128::from(combat.monster_health);
+    let mut turn_damage: i128 = damage.iter().sum();
+    let mut start = 0;
+    let mut next_death = 0;
+    let mut next_boost = 0;
+    loop {
+        while next_death < by_death.len() && attack_turns[by_death[next_death]] <= start {
+            turn_damage -= damage[by_death[next_death]];
+            next_death += 1;
+        }
+        // A boost on a hero who no longer attacks changes nothing.
+        while let Some(&(turn, hero, boost)) = combat.late_boosts.get(next_boost) {
+            if turn > start {
+                break;
+            }
+            damage[hero] += i128::from(boost);
+            if attack_turns[hero] > start {
+                turn_damage += i128::from(boost);
+            }
+            next_boost += 1;
+        }
+        let end = match by_death.get(next_death) {
+            Some(&i) => match combat.late_boosts.get(next_boost) {
+                Some(&(turn, _, _)) => cmp::min(turn, attack_turns[i]),
+                None => attack_turns[i],
+            },
+            None => break,
+        };
+
+        // The monster dies on the first turn that starts with at most
+        // turn_damage health, if that is before the phase ends.
+        // The product can still overflow on a long enough phase, but then it
+        // is certainly enough.
+        let phase_turns = (end - start) as i128;
+        let phase_damage = turn_damage.saturating_mul(phase_turns);
+        if turn_damage > 0 && health <= phase_damage {
+            let skipped = cmp::max(0, (health - 1) / turn_damage);
+            let turn = start + skipped as usize;
+            health -= skipped * turn_damage;
+            for i in attack_order(&combat.heroes) {
+                if attack_turns[i] <= turn {
+                    continue;
+                }
+                health -= damage[i];
+                // The health was positive before this hit, so it still
+                // fits in an i64.
+                if health <= 0 {
+                    return Ok(Outcome {
+                        killer: Some(i),
+                        turn,
+                        monster_health: health as i64,
+                    });
+                }
+            }
+            unreachable!("the monster survived a turn of {} damage", turn_damage);
+        }
+        health -= phase_damage;
+        start = end;
+    }
+
+    // Everyone is dead. With the monster going first, the last hero dies on
+    // the first turn they don't attack, otherwise on the last one they do.
+    let last_death = match combat.rules.turn_order {
+        TurnOrder::HeroesFirst => start.saturating_sub(1),
+        TurnOrder::MonsterFirst => start,
+    };
+    Ok(Outcome {
+        killer: None,
+        turn: last_death,
+        monster_health: health as i64,
+    })
+}
+
+fn fight(combat: &Combat, trace: Option<&mut Vec<TurnRecord>>) -> Outcome {
+    fight_with(combat, trace, &mut |_| 0)
+}
+
+// Fights `trials` times with each attack's damage drawn from the hero's
+// range, and counts the times the chosen hero lands the killing blow.
+pub(crate) fn count_random_wins(
+    problem: &Problem,
+    boosts: &[usize],
+    trials: u64,
+    rng: &mut Rng,
+) -> Result<u64, MonsterError> {
+    let combat = apply_boosts(problem, boosts, &[])?;
+    // How much more than their least damage each hero can deal. The boosted
+    // range fits, so this does too.
+    let spread: Vec<u64> = combat
+        .heroes
+        .iter()
+        .map(|hero| hero.max_damage.map_or(0, |max| (max - hero.damage) as u64))
+        .collect();
+    let mut roll = |hero: usize| match spread[hero] {
+        0 => 0,
+        spread => rng.between(0, spread) as i64,
+    };
+    let mut wins = 0;
+    for _ in 0..trials {
+        if fight_with(&combat, None, &mut roll).killer == Some(problem.chosen_hero) {
+            wins += 1;
+        }
+    }
+    Ok(wins)
+}
+
+// `roll` gives how much damage each attack of the hero deals on top of the
+// least they can.
+fn fight_with(
+    combat: &Combat,
+    mut trace: Option<&mut Vec<TurnRecord>>,
+    roll: &mut dyn FnMut(usize) -> i64,
+) -> Outcome {
+    let monster_first = combat.rules.turn_order == TurnOrder::MonsterFirst;
     let mut remaining_monster_health = combat.monster_health;
-    let mut first_hero_index = 0;
-    let mut first_hero_damage = 0;
-    while first_hero_index < combat.heroes.len() {
-        for i in first_hero_index..combat.heroes.len() {
-            remaining_monster_health -= combat.heroes[i].damage;
+    let mut hero_health: Vec<usize> = combat.heroes.iter().map(|hero| hero.health).collect();
+    let mut damage: Vec<i64> = combat.heroes.iter().map(|hero| hero.damage).collect();
+    let mut boosts = combat.late_boosts.iter().peekable();
+    let order = attack_order(&combat.heroes);
+    let mut alive = combat.heroes.len();
+    let mut turn = 0;
+    while alive > 0 {
+        while let Some(&&(_, hero, boost)) = boosts.peek().filter(|boost| boost.0 <= turn) {
+            damage[hero] += boost;
+            boosts.next();
+        }
+        let mut deaths = Vec::new();
+        if monster_first {
+            deaths = monster_attack(&combat.rules.monster_attack, turn, &mut hero_health);
+        }
+        let mut attacks = Vec::new();
+        let mut killer = None;
+        for &i in &order {
+            if hero_health[i] == 0 {
+                continue;
+            }
+            let damage = damage[i] + roll(i);
+            remaining_monster_health -= damage;
+            attacks.push(Attack { hero: i, damage });
             if remaining_monster_health <= 0 {
-                return Some(i);
+                killer = Some(i);
+                break;
             }
         }
-        first_hero_damage += 1;
-        if first_hero_damage == combat.heroes[first_hero_index].health {
-            first_hero_index += 1;
-            first_hero_damage = 0;
+        if killer.is_none() && !monster_first {
+            deaths = monster_attack(&combat.rules.monster_attack, turn, &mut hero_health);
+        }
+        alive -= deaths.len();
+        if let Some(ref mut trace) = trace {
+            trace.push(TurnRecord {
+                turn,
+                attacks,
+                monster_health: remaining_monster_health,
+                deaths,
+            });
         }
+        if killer.is_some() {
+            return Outcome {
+                killer,
+                turn,
+                monster_health: remaining_monster_health,
+            };
+        }
+        if alive > 0 {
+            turn += 1;
+        }
+    }
+    Outcome {
+        killer: None,
+        turn,
+        monster_health: remaining_monster_health,
     }
-    None
+}
+
+// Returns the heroes who die from the monster's attack.
+fn monster_attack(attack: &MonsterAttack, turn: usize, hero_health: &mut [usize]) -> Vec<usize> {
+    let damage = attack.damage_on(turn);
+    let living = (0..hero_health.len()).filter(|&i| hero_health[i] > 0);
+    let targets: Vec<usize> = match attack.target {
+        Target::Front => living.take(1).collect(),
+        Target::All => living.collect(),
+    };
+    targets
+        .into_iter()
+        .filter(|&i| {
+            hero_health[i] = hero_health[i].saturating_sub(damage);
+            hero_health[i] == 0
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use generate::{Generator, GeneratorConfig};
+    use rng::Rng;
+
+    fn assert_invalid_boosts(result: Result<Combat, MonsterError>) {
+        match result {
+            Err(MonsterError::InvalidBoosts(_)) => {}
+            other => panic!("Expected invalid boosts, got {:?}", other),
+        }
+    }
 
     #[test]
     fn valid_solution() {
-        assert!(solution_is_valid(
-            &Problem {
-                monster_health: 14,
-                heroes: vec![
-                    Hero {
-                        health: 10,
-                        damage: 4
-                    },
-                    Hero {
-                        health: 10,
-                        damage: 4
-                    }
-                ],
-                chosen_hero: 0,
-                boost_damage: 4,
-                max_boosts: 1,
-            },
-            &[1]
-        ))
+        assert_eq!(
+            Ok(true),
+            solution_is_valid(
+                &Problem {
+                    monster_health: 14,
+                    heroes: vec![Hero::new(10, 4), Hero::new(10, 4)],
+                    chosen_hero: 0,
+                    boost_damage: 4,
+                    max_boosts: 1,
+                    rules: Rules::default(),
+                },
+                &[1]
+            )
+        )
     }
 
     #[test]
     fn invalid_solution() {
-        assert!(!solution_is_valid(
-            &Problem {
-                monster_health: 14,
-                heroes: vec![
-                    Hero {
-                        health: 10,
-                        damage: 4
-                    },
-                    Hero {
-                        health: 10,
-                        damage: 4
-                    }
-                ],
-                chosen_hero: 0,
-                boost_damage: 4,
-                max_boosts: 2,
-            },
-            &[1, 1]
-        ))
+        assert_eq!(
+            Ok(false),
+            solution_is_valid(
+                &Problem {
+                    monster_health: 14,
+                    heroes: vec![Hero::new(10, 4), Hero::new(10, 4)],
+                    chosen_hero: 0,
+                    boost_damage: 4,
+                    max_boosts: 2,
+                    rules: Rules::default(),
+                },
+                &[1, 1]
+            )
+        )
     }
 
     #[test]
     fn no_heroes_no_boosts() {
         assert_eq!(
-            Combat {
+            Ok(Combat {
                 monster_health: 10,
-                heroes: vec![]
-            },
+                heroes: vec![],
+                rules: Rules::default(),
+                late_boosts: vec![],
+            }),
             apply_boosts(
                 &Problem {
                     monster_health: 10,
                     heroes: vec![],
                     chosen_hero: 0,
                     max_boosts: 0,
-                    boost_damage: 0
+                    boost_damage: 0,
+                    rules: Rules::default(),
                 },
+                &[],
                 &[]
             )
         )
@@ -122,36 +582,22 @@ mod tests {
     #[test]
     fn two_heroes_no_boosts() {
         assert_eq!(
-            Combat {
+            Ok(Combat {
                 monster_health: 10,
-                heroes: vec![
-                    Hero {
-                        health: 10,
-                        damage: 5
-                    },
-                    Hero {
-                        health: 10,
-                        damage: 10
-                    }
-                ]
-            },
+                heroes: vec![Hero::new(10, 5), Hero::new(10, 10)],
+                rules: Rules::default(),
+                late_boosts: vec![],
+            }),
             apply_boosts(
                 &Problem {
                     monster_health: 10,
-                    heroes: vec![
-                        Hero {
-                            health: 10,
-                            damage: 5
-                        },
-                        Hero {
-                            health: 10,
-                            damage: 10
-                        }
-                    ],
+                    heroes: vec![Hero::new(10, 5), Hero::new(10, 10)],
                     chosen_hero: 0,
                     max_boosts: 0,
-                    boost_damage: 0
+                    boost_damage: 0,
+                    rules: Rules::default(),
                 },
+                &[],
                 &[]
             )
         )
@@ -160,92 +606,126 @@ mod tests {
     #[test]
     fn three_heroes_three_boosts() {
         assert_eq!(
-            Combat {
+            Ok(Combat {
                 monster_health: 10,
-                heroes: vec![
-                    Hero {
-                        health: 10,
-                        damage: 5
-                    },
-                    Hero {
-                        health: 10,
-                        damage: 20
-                    },
-                    Hero {
-                        health: 10,
-                        damage: 10
-                    },
-                ]
-            },
+                heroes: vec![Hero::new(10, 5), Hero::new(10, 20), Hero::new(10, 10),],
+                rules: Rules::default(),
+                late_boosts: vec![],
+            }),
             apply_boosts(
                 &Problem {
                     monster_health: 10,
-                    heroes: vec![
-                        Hero {
-                            health: 10,
-                            damage: 3
-                        },
-                        Hero {
-                            health: 10,
-                            damage: 20
-                        },
-                        Hero {
-                            health: 10,
-                            damage: 6
-                        },
-                    ],
+                    heroes: vec![Hero::new(10, 3), Hero::new(10, 20), Hero::new(10, 6),],
                     chosen_hero: 0,
                     max_boosts: 3,
-                    boost_damage: 2
+                    boost_damage: 2,
+                    rules: Rules::default(),
                 },
-                &[2, 0, 2]
+                &[2, 0, 2],
+                &[]
             )
         )
     }
 
     #[test]
-    #[should_panic]
     fn one_hero_too_many_boosts() {
-        apply_boosts(
+        assert_invalid_boosts(apply_boosts(
             &Problem {
                 monster_health: 10,
-                heroes: vec![Hero {
-                    health: 10,
-                    damage: 3,
-                }],
+                heroes: vec![Hero::new(10, 3)],
                 chosen_hero: 0,
                 max_boosts: 1,
                 boost_damage: 2,
+                rules: Rules::default(),
             },
             &[0, 0, 0],
-        );
+            &[],
+        ));
     }
 
     #[test]
-    #[should_panic]
     fn boost_out_of_bounds() {
-        apply_boosts(
+        assert_invalid_boosts(apply_boosts(
             &Problem {
                 monster_health: 10,
-                heroes: vec![Hero {
-                    health: 10,
-                    damage: 3,
-                }],
+                heroes: vec![Hero::new(10, 3)],
                 chosen_hero: 0,
                 max_boosts: 1,
                 boost_damage: 2,
+                rules: Rules::default(),
             },
             &[2],
-        );
+            &[],
+        ));
+    }
+
+    #[test]
+    fn health_boosts_and_caps() {
+        let problem = Problem {
+            monster_health: 5,
+            heroes: vec![Hero::new(1, 2)],
+            chosen_hero: 0,
+            max_boosts: 1,
+            boost_damage: 1,
+            rules: Rules {
+                health_boost: 2,
+                max_health_boosts: 2,
+                boost_caps: vec![2],
+                ..Rules::default()
+            },
+        };
+        // Three hits let the hero attack three times.
+        assert_eq!(Ok(false), solution_is_valid(&problem, &[]));
+        assert_eq!(Ok(true), solution_is_valid_with_health(&problem, &[], &[0]));
+        assert_invalid_boosts(apply_boosts(&problem, &[], &[0, 0, 0]));
+        assert_invalid_boosts(apply_boosts(&problem, &[0], &[0, 0]));
+        assert_invalid_boosts(apply_boosts(&problem, &[], &[1]));
+    }
+
+    #[test]
+    fn boost_overflows() {
+        match apply_boosts(
+            &Problem {
+                monster_health: 10,
+                heroes: vec![Hero::new(10, i64::MAX)],
+                chosen_hero: 0,
+                max_boosts: 1,
+                boost_damage: 1,
+                rules: Rules::default(),
+            },
+            &[0],
+            &[],
+        ) {
+            Err(MonsterError::Overflow(_)) => {}
+            other => panic!("Expected an overflow, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn invalid_chosen_hero() {
+        assert!(solution_is_valid(
+            &Problem {
+                monster_health: 10,
+                heroes: vec![Hero::new(10, 3)],
+                chosen_hero: 1,
+                max_boosts: 0,
+                boost_damage: 0,
+                rules: Rules::default(),
+            },
+            &[],
+        )
+        .is_err());
     }
 
     #[test]
     fn empty_combat() {
         assert_eq!(
-            None,
+            Ok(None),
             find_hero_with_killing_blow(&Combat {
                 monster_health: 10,
-                heroes: vec![]
+                heroes: vec![],
+                rules: Rules::default(),
+                late_boosts: vec![],
             })
         );
     }
@@ -253,13 +733,12 @@ mod tests {
     #[test]
     fn last_hero_dies() {
         assert_eq!(
-            None,
+            Ok(None),
             find_hero_with_killing_blow(&Combat {
                 monster_health: 10,
-                heroes: vec![Hero {
-                    health: 2,
-                    damage: 1
-                }]
+                heroes: vec![Hero::new(2, 1)],
+                rules: Rules::default(),
+                late_boosts: vec![],
             })
         );
     }
@@ -267,13 +746,12 @@ mod tests {
     #[test]
     fn single_hero() {
         assert_eq!(
-            Some(0),
+            Ok(Some(0)),
             find_hero_with_killing_blow(&Combat {
                 monster_health: 10,
-                heroes: vec![Hero {
-                    health: 100,
-                    damage: 1
-                }]
+                heroes: vec![Hero::new(100, 1)],
+                rules: Rules::default(),
+                late_boosts: vec![],
             })
         );
     }
@@ -281,19 +759,12 @@ mod tests {
     #[test]
     fn two_heroes() {
         assert_eq!(
-            Some(1),
+            Ok(Some(1)),
             find_hero_with_killing_blow(&Combat {
                 monster_health: 10,
-                heroes: vec![
-                    Hero {
-                        health: 100,
-                        damage: 1
-                    },
-                    Hero {
-                        health: 100,
-                        damage: 5
-                    }
-                ]
+                heroes: vec![Hero::new(100, 1), Hero::new(100, 5)],
+                rules: Rules::default(),
+                late_boosts: vec![],
             })
         );
     }
@@ -301,19 +772,12 @@ mod tests {
     #[test]
     fn two_heroes_one_dies() {
         assert_eq!(
-            Some(1),
+            Ok(Some(1)),
             find_hero_with_killing_blow(&Combat {
                 monster_health: 15,
-                heroes: vec![
-                    Hero {
-                        health: 2,
-                        damage: 1
-                    },
-                    Hero {
-                        health: 100,
-                        damage: 2
-                    }
-                ]
+                heroes: vec![Hero::new(2, 1), Hero::new(100, 2)],
+                rules: Rules::default(),
+                late_boosts: vec![],
             })
         );
     }
@@ -321,24 +785,352 @@ mod tests {
     #[test]
     fn three_heroes_one_dies() {
         assert_eq!(
-            Some(1),
+            Ok(Some(1)),
             find_hero_with_killing_blow(&Combat {
                 monster_health: 50,
-                heroes: vec![
-                    Hero {
-                        health: 2,
-                        damage: 1,
-                    },
-                    Hero {
-                        health: 100,
-                        damage: 10,
-                    },
-                    Hero {
-                        health: 100,
-                        damage: 4,
-                    }
-                ]
+                heroes: vec![Hero::new(2, 1), Hero::new(100, 10), Hero::new(100, 4)],
+                rules: Rules::default(),
+                late_boosts: vec![],
             })
         );
     }
+
+    #[test]
+    fn outcome_turns() {
+        let survivor = fight(
+            &Combat {
+                monster_health: 10,
+                heroes: vec![Hero::new(2, 1)],
+                rules: Rules::default(),
+                late_boosts: vec![],
+            },
+            None,
+        );
+        assert_eq!(
+            Outcome {
+                killer: None,
+                turn: 1,
+                monster_health: 8
+            },
+            survivor
+        );
+        let problem = Problem {
+            monster_health: 10,
+            heroes: vec![Hero::new(100, 1)],
+            chosen_hero: 0,
+            max_boosts: 1,
+            boost_damage: 2,
+            rules: Rules::default(),
+        };
+        assert_eq!(
+            Ok(Outcome {
+                killer: Some(0),
+                turn: 3,
+                monster_health: -2
+            }),
+            simulate(&problem, &[0])
+        );
+    }
+
+    fn trace_problem() -> Problem {
+        Problem {
+            monster_health: 12,
+            heroes: vec![Hero::new(1, 3), Hero::new(5, 2)],
+            chosen_hero: 1,
+            max_boosts: 1,
+            boost_damage: 1,
+            rules: Rules::default(),
+        }
+    }
+
+    #[test]
+    fn trace_records_turns() {
+        let trace = simulate_trace(&trace_problem(), &[1]).unwrap();
+        assert_eq!(
+            vec![
+                TurnRecord {
+                    turn: 0,
+                    attacks: vec![Attack { hero: 0, damage: 3 }, Attack { hero: 1, damage: 3 }],
+                    monster_health: 6,
+                    deaths: vec![0],
+                },
+                TurnRecord {
+                    turn: 1,
+                    attacks: vec![Attack { hero: 1, damage: 3 }],
+                    monster_health: 3,
+                    deaths: vec![],
+                },
+                TurnRecord {
+                    turn: 2,
+                    attacks: vec![Attack { hero: 1, damage: 3 }],
+                    monster_health: 0,
+                    deaths: vec![],
+                },
+            ],
+            trace.turns
+        );
+        assert_eq!(simulate(&trace_problem(), &[1]), Ok(trace.outcome));
+    }
+
+    #[test]
+    fn faster_hero_attacks_first() {
+        // The hero behind is faster, so the front hero strikes last.
+        let mut problem = Problem {
+            monster_health: 5,
+            heroes: vec![
+                Hero::new(5, 2),
+                Hero {
+                    speed: 1,
+                    ..Hero::new(5, 3)
+                },
+            ],
+            chosen_hero: 0,
+            boost_damage: 0,
+            max_boosts: 0,
+            rules: Rules::default(),
+        };
+        let trace = simulate_trace(&problem, &[]).unwrap();
+        assert_eq!(
+            vec![Attack { hero: 1, damage: 3 }, Attack { hero: 0, damage: 2 }],
+            trace.turns[0].attacks
+        );
+        assert_eq!(Some(0), trace.outcome.killer);
+        assert_eq!(Ok(trace.outcome), simulate(&problem, &[]));
+
+        // Equally fast, they attack in the order they stand in.
+        problem.heroes[0].speed = 1;
+        assert_eq!(Ok(false), solution_is_valid(&problem, &[]));
+    }
+
+    #[test]
+    fn trace_table() {
+        let trace = simulate_trace(&trace_problem(), &[]).unwrap();
+        assert_eq!(
+            "turn  hero 0  hero 1  monster  deaths\n\
+             \x20  0       3       2        7  hero 0\n\
+             \x20  1       -       2        5\n\
+             \x20  2       -       2        3\n\
+             \x20  3       -       2        1\n\
+             \x20  4       -       2       -1\n",
+            trace.to_table()
+        );
+    }
+
+    #[test]
+    fn trace_json() {
+        let trace = simulate_trace(&trace_problem(), &[0]).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&trace.to_json()).unwrap();
+        assert_eq!(4, value["turns"].as_array().unwrap().len());
+        assert_eq!(1, value["outcome"]["killer"]);
+        assert_eq!(4, value["turns"][0]["attacks"][0]["damage"]);
+    }
+
+    #[test]
+    fn monster_first_trace() {
+        let mut problem = trace_problem();
+        problem.rules.turn_order = TurnOrder::MonsterFirst;
+        let trace = simulate_trace(&problem, &[]).unwrap();
+        // The front hero dies before attacking, which spares the other
+        // hero a hit on the first turn.
+        assert_eq!(
+            TurnRecord {
+                turn: 0,
+                attacks: vec![Attack { hero: 1, damage: 2 }],
+                monster_health: 10,
+                deaths: vec![0],
+            },
+            trace.turns[0]
+        );
+        assert_eq!(
+            Outcome {
+                killer: None,
+                turn: 5,
+                monster_health: 2
+            },
+            trace.outcome
+        );
+        assert_eq!(
+            TurnRecord {
+                turn: 5,
+                attacks: vec![],
+                monster_health: 2,
+                deaths: vec![1],
+            },
+            trace.turns[5]
+        );
+    }
+
+    #[test]
+    fn turn_order_decides_killer() {
+        let mut problem = trace_problem();
+        problem.monster_health = 11;
+        assert_eq!(Ok(true), solution_is_valid(&problem, &[]));
+        problem.rules.turn_order = TurnOrder::MonsterFirst;
+        assert_eq!(Ok(false), solution_is_valid(&problem, &[]));
+        assert_eq!(Ok(true), solution_is_valid(&problem, &[1]));
+    }
+
+    #[test]
+    fn phases_match_turn_by_turn() {
+        let variants = [
+            (TurnOrder::HeroesFirst, Target::Front, 1, 0),
+            (TurnOrder::MonsterFirst, Target::Front, 2, 0),
+            (TurnOrder::HeroesFirst, Target::All, 1, 1),
+            (TurnOrder::MonsterFirst, Target::All, 0, 3),
+        ];
+        let mut rng = Rng::new(5);
+        for (seed, &(turn_order, target, damage, growth)) in variants.iter().enumerate() {
+            let config = GeneratorConfig {
+                max_health: 40,
+                max_damage: 6,
+                rules: Rules {
+                    turn_order,
+                    monster_attack: MonsterAttack {
+                        damage,
+                        target,
+                        growth,
+                    },
+                    ..Rules::default()
+                },
+                ..GeneratorConfig::default()
+            };
+            for problem in Generator::new(seed as u64, config).take(500) {
+                let boosts: Vec<usize> = (0..rng.between(0, problem.max_boosts as u64))
+                    .map(|_| rng.between(0, problem.heroes.len() as u64 - 1) as usize)
+                    .collect();
+                let combat = apply_boosts(&problem, &boosts, &[]).unwrap();
+                assert_eq!(
+                    Ok(fight(&combat, None)),
+                    fight_by_phases(&combat),
+                    "{:?} with boosts {:?}",
+                    problem,
+                    boosts
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn phases_match_turn_by_turn_with_speeds() {
+        let config = GeneratorConfig {
+            max_health: 40,
+            max_damage: 6,
+            max_speed: 2,
+            ..GeneratorConfig::default()
+        };
+        let mut rng = Rng::new(8);
+        for problem in Generator::new(9, config).take(500) {
+            let boosts: Vec<usize> = (0..rng.between(0, problem.max_boosts as u64))
+                .map(|_| rng.between(0, problem.heroes.len() as u64 - 1) as usize)
+                .collect();
+            let combat = apply_boosts(&problem, &boosts, &[]).unwrap();
+            assert_eq!(
+                Ok(fight(&combat, None)),
+                fight_by_phases(&combat),
+                "{:?} with boosts {:?}",
+                problem,
+                boosts
+            );
+        }
+    }
+
+    #[test]
+    fn scheduled_phases_match_turn_by_turn() {
+        let config = GeneratorConfig {
+            max_health: 40,
+            max_damage: 6,
+            ..GeneratorConfig::default()
+        };
+        let mut rng = Rng::new(6);
+        for problem in Generator::new(7, config).take(500) {
+            let attack_turns = attack_turns(&problem.heroes, &problem.rules).unwrap();
+            let mut schedule = Vec::new();
+            for _ in 0..rng.between(0, problem.max_boosts as u64) {
+                let hero = rng.between(0, problem.heroes.len() as u64 - 1) as usize;
+                if attack_turns[hero] > 0 {
+                    let turn = rng.between(0, attack_turns[hero] as u64 - 1) as usize;
+                    schedule.push((turn, hero));
+                }
+            }
+            let combat = apply_schedule(&problem, &schedule).unwrap();
+            assert_eq!(
+                Ok(fight(&combat, None)),
+                fight_by_phases(&combat),
+                "{:?} with schedule {:?}",
+                problem,
+                schedule
+            );
+        }
+    }
+
+    #[test]
+    fn illegal_schedules() {
+        let mut problem = Problem {
+            monster_health: 10,
+            heroes: vec![Hero::new(1, 1), Hero::new(5, 1)],
+            chosen_hero: 1,
+            boost_damage: 2,
+            max_boosts: 2,
+            rules: Rules {
+                boosts_per_turn: Some(1),
+                ..Rules::default()
+            },
+        };
+        assert_eq!(Ok(()), validate_schedule(&problem, &[(0, 0), (1, 1)]));
+        // The front hero dies at the end of the first turn.
+        assert_invalid_boosts(apply_schedule(&problem, &[(1, 0)]));
+        assert_invalid_boosts(apply_schedule(&problem, &[(2, 1), (2, 1)]));
+        assert_invalid_boosts(apply_schedule(&problem, &[(0, 2)]));
+        assert_invalid_boosts(apply_schedule(&problem, &[(0, 1), (1, 1), (2, 1)]));
+        problem.rules.boosts_per_turn = None;
+        assert_eq!(Ok(()), validate_schedule(&problem, &[(2, 1), (2, 1)]));
+    }
+
+    #[test]
+    fn boost_from_a_later_turn() {
+        // Boosted from the start, the chosen hero's 3 damage a turn would let
+        // the front hero kill the monster on the second turn.
+        let problem = Problem {
+            monster_health: 7,
+            heroes: vec![Hero::new(5, 2), Hero::new(5, 1)],
+            chosen_hero: 1,
+            boost_damage: 2,
+            max_boosts: 1,
+            rules: Rules::default(),
+        };
+        assert_eq!(Ok(false), solution_is_valid(&problem, &[1]));
+        assert_eq!(Ok(false), schedule_is_valid(&problem, &[(0, 1)]));
+        assert_eq!(Ok(true), schedule_is_valid(&problem, &[(1, 1)]));
+        assert_eq!(
+            Ok(Outcome {
+                killer: Some(1),
+                turn: 1,
+                monster_health: -1,
+            }),
+            simulate_schedule(&problem, &[(1, 1)])
+        );
+    }
+
+    #[test]
+    fn huge_monster_health() {
+        // Going turn by turn this takes a billion steps; the chosen hero
+        // lands the blow on the last turn the front hero is alive.
+        let problem = Problem {
+            monster_health: 2000000000,
+            heroes: vec![Hero::new(1000000000, 1), Hero::new(3000000000, 1)],
+            chosen_hero: 1,
+            boost_damage: 0,
+            max_boosts: 0,
+            rules: Rules::default(),
+        };
+        assert_eq!(
+            Ok(Outcome {
+                killer: Some(1),
+                turn: 999999999,
+                monster_health: 0,
+            }),
+            simulate(&problem, &[])
+        );
+        assert_eq!(Ok(true), solution_is_valid(&problem, &[]));
+    }
 }
@@ -1,313 +1,996 @@
-use lp_modeler::dsl::*;
-use lp_modeler::format::lp_format::LpFileFormat;
-use lp_modeler::solvers::{CbcSolver, SolverTrait, Status};
-use problem::{Hero, Problem};
+use backend::{Backend, MonsterSolver};
+use error::{MonsterError, OrOverflow};
+use problem::{attack_order, Hero, MonsterAttack, Problem, Rules, Target, TurnOrder};
 
-use std::ops::Add;
+use std::cmp;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use std::vec;
 
 // Describes the behaviour of combat throughout the time
 // that a hero does not die.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub(crate) struct CombatPhase {
+    pub(crate) end_stage: usize,
+    // Damage of the living heroes attacking before and after the chosen one.
+    pub(crate) early_damage: i64,
+    pub(crate) late_damage: i64,
+}
+
 #[derive(Debug, PartialEq, Eq)]
-struct CombatPhase {
-    end_stage: usize,
-    early_damage: i32,
+pub(crate) struct Combat {
+    // The last phase ends when the chosen hero dies.
+    pub(crate) phases: Vec<CombatPhase>,
+    // The heroes worth boosting, by index, with the number of turns each of
+    // them attacks. A hero attacking after the chosen one who outlives them is
+    // never worth it: a boost on the chosen hero adds as much damage before the
+    // killing blow, and more with it. Unless the chosen hero's cap may stop
+    // them from taking every boost, that is.
+    pub(crate) boostable: Vec<(usize, usize)>,
+    pub(crate) chosen_hero: usize,
+    pub(crate) chosen_damage: i64,
+    // How many damage boosts each hero can still take.
+    pub(crate) caps: Vec<usize>,
+    // Where each hero comes in the order of attacks within a turn.
+    pub(crate) ranks: Vec<usize>,
+}
+
+impl Combat {
+    // How many times a boostable hero attacks before the chosen hero's attack
+    // on the given turn.
+    pub(crate) fn attacks_before_chosen(
+        &self,
+        hero: usize,
+        attack_turns: usize,
+        turn: usize,
+    ) -> usize {
+        if self.ranks[hero] < self.ranks[self.chosen_hero] {
+            cmp::min(attack_turns, turn + 1)
+        } else {
+            cmp::min(attack_turns, turn)
+        }
+    }
+
+    // The turns on which the chosen hero could land the killing blow with at
+    // most `max_boosts` boosts, in order. The phases are worked out up front,
+    // so that any overflow is reported here rather than halfway through.
+    pub(crate) fn stages(
+        &self,
+        problem: &Problem,
+        max_boosts: usize,
+    ) -> Result<Stages, MonsterError> {
+        let max_boost_damage = max_boost_damage(problem, max_boosts)?;
+        let windows = self
+            .windows(problem)?
+            .into_iter()
+            .map(|window| {
+                let turns = window.candidate_turns(self.chosen_damage, max_boost_damage)?;
+                Ok((window, turns))
+            })
+            .collect::<Result<Vec<_>, MonsterError>>()?;
+        Ok(Stages {
+            windows: windows.into_iter(),
+            window: None,
+            turns: 0..0,
+            chosen_damage: self.chosen_damage,
+        })
+    }
+
+    // The phases as seen by the chosen hero, up to the one in which the
+    // monster dies unboosted, if it does.
+    pub(crate) fn windows(&self, problem: &Problem) -> Result<Vec<PhaseWindow>, MonsterError> {
+        let mut windows = Vec::new();
+        let mut remaining_health = problem.monster_health;
+        let mut start = 0;
+        for phase in &self.phases {
+            let turn_damage = phase
+                .early_damage
+                .checked_add(self.chosen_damage)
+                .and_then(|damage| damage.checked_add(phase.late_damage))
+                .or_overflow("the damage dealt in a turn")?;
+            // The remaining health is positive, so this stays in range.
+            windows.push(PhaseWindow {
+                start,
+                end: phase.end_stage,
+                health: remaining_health - phase.early_damage,
+                turn_damage,
+            });
+
+            // Boosts only ever add damage, so once the monster dies unboosted
+            // no later turn can work either. A phase whose damage overflows
+            // certainly kills it.
+            let phase_damage =
+                to_i64(phase.end_stage - start, "the length of a phase")?.checked_mul(turn_damage);
+            match phase_damage {
+                Some(damage) if damage < remaining_health => remaining_health -= damage,
+                _ => break,
+            }
+            start = phase.end_stage;
+        }
+        Ok(windows)
+    }
+}
+
+// The most any boosts can add per turn, which is by all going to the chosen
+// hero.
+pub(crate) fn max_boost_damage(problem: &Problem, max_boosts: usize) -> Result<i64, MonsterError> {
+    to_i64(max_boosts, "the number of boosts")?
+        .checked_mul(problem.boost_damage)
+        .or_overflow("the damage of all boosts together")
+}
+
+// The chosen hero striking the killing blow on a particular turn. All the
+// health values are relative to the unboosted combat.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) struct Stage {
+    pub(crate) turn: usize,
+    // Boosted damage before the chosen hero must stay below this...
+    pub(crate) pre_limit: i64,
+    // ...and the chosen hero's boosted damage must reach this.
+    pub(crate) post_need: i64,
 }
 
-struct DynVariable {
-    index: usize,
-    rep: LpInteger,
-    label: String,
+pub(crate) struct Stages {
+    windows: vec::IntoIter<(PhaseWindow, Range<usize>)>,
+    window: Option<PhaseWindow>,
+    turns: Range<usize>,
+    chosen_damage: i64,
 }
 
-#[allow(dead_code)]
-impl DynVariable {
-    pub fn new(name: &str, index: usize) -> DynVariable {
-        let label = format!("{}_{}", name, index);
-        DynVariable {
-            index,
-            rep: LpInteger::new(label.as_str()),
-            label,
+impl Iterator for Stages {
+    type Item = Stage;
+
+    fn next(&mut self) -> Option<Stage> {
+        loop {
+            if let Some(turn) = self.turns.next() {
+                // The monster is alive on candidate turns, so the health is
+                // at least one and neither difference overflows.
+                let health = self.window.as_ref()?.health_before_chosen(turn);
+                return Some(Stage {
+                    turn,
+                    pre_limit: health - 1,
+                    post_need: health - self.chosen_damage,
+                });
+            }
+            let (window, turns) = self.windows.next()?;
+            self.window = Some(window);
+            self.turns = turns;
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-struct Combat {
-    phases: Vec<CombatPhase>,
-    late_damage: i32,
-    chosen_damage: i32,
+// The turns [start, end) of a phase, during which the monster takes the same
+// unboosted damage every turn.
+pub(crate) struct PhaseWindow {
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    // Health before the chosen hero attacks on the first turn.
+    pub(crate) health: i64,
+    pub(crate) turn_damage: i64,
 }
 
-#[allow(dead_code)]
-pub fn solve(problem: &Problem) -> Option<Vec<usize>> {
-    let combat = build_combat(problem);
-    let boosts: Vec<DynVariable> = (0..=problem.chosen_hero)
-        .map(|i| DynVariable::new("boost", i))
-        .collect();
-    let chosen_hero_boost = &boosts.last().unwrap();
-    let mut stages: Vec<LpBinary> = Vec::new();
-    let mut stage_labels: Vec<String> = Vec::new();
-    let mut lp = LpProblem::new("Monster", LpObjective::Maximize);
+impl PhaseWindow {
+    // Only meant for the candidate turns, on which the damage so far is less
+    // than the health.
+    fn health_before_chosen(&self, turn: usize) -> i64 {
+        self.health - (turn - self.start) as i64 * self.turn_damage
+    }
 
-    {
-        let max_boost_damage = problem.max_boosts as i32 * problem.boost_damage;
+    // The turns on which the monster is still alive when the chosen hero
+    // attacks, and on which the boosts could deal enough damage to kill it.
+    fn candidate_turns(
+        &self,
+        chosen_damage: i64,
+        max_boost_damage: i64,
+    ) -> Result<Range<usize>, MonsterError> {
+        let first = self.first_reachable(chosen_damage, max_boost_damage)?;
+        let last = self.alive_until();
+        Ok(if first < last {
+            first..last
+        } else {
+            self.start..self.start
+        })
+    }
 
-        let mut remaining_health = problem.monster_health;
-        let mut active_phase = 0;
+    // The first turn of the window on which the monster is dead before the
+    // chosen hero attacks, or the end if there is none.
+    pub(crate) fn alive_until(&self) -> usize {
+        // The monster survives until the chosen hero attacks as long as
+        // health - (turn - start) * turn_damage >= 1.
+        if self.health <= 0 {
+            self.start
+        } else if self.turn_damage == 0 {
+            self.end
+        } else {
+            let alive_turns = (self.health - 1) / self.turn_damage + 1;
+            cmp::min(self.end, self.start.saturating_add(alive_turns as usize))
+        }
+    }
 
-        set_max_boosts(&mut lp, &boosts, problem.max_boosts);
+    // The first turn of the window from which on the chosen hero could deal
+    // enough damage with every boost, or the end if there is none.
+    pub(crate) fn first_reachable(
+        &self,
+        chosen_damage: i64,
+        max_boost_damage: i64,
+    ) -> Result<usize, MonsterError> {
+        // With every boost on the chosen hero we need
+        // health - (turn - start) * turn_damage - chosen_damage
+        //     <= (turn + 1) * max_boost_damage.
+        let rate = self
+            .turn_damage
+            .checked_add(max_boost_damage)
+            .or_overflow("the damage dealt in a turn")?;
+        let shortfall = to_i64(self.start, "the turn number")?
+            .checked_mul(self.turn_damage)
+            .and_then(|damage| damage.checked_add(self.health - chosen_damage))
+            .and_then(|shortfall| shortfall.checked_sub(max_boost_damage))
+            .or_overflow("the damage needed from boosts")?;
+        Ok(if shortfall <= 0 {
+            self.start
+        } else if rate == 0 {
+            self.end
+        } else {
+            let turn = usize::try_from(ceil_div(shortfall, rate)).unwrap_or(usize::MAX);
+            cmp::min(self.end, cmp::max(self.start, turn))
+        })
+    }
+}
 
-        for turn in 0..combat.phases.last().unwrap().end_stage {
-            if turn == combat.phases[active_phase].end_stage {
-                active_phase += 1;
-            }
-            remaining_health -= combat.phases[active_phase].early_damage;
-
-            if (turn as i32 + 1) * max_boost_damage + combat.chosen_damage >= remaining_health {
-                stage_labels.push(format!("stage_{}", turn));
-                stages.push(LpBinary::new(stage_labels.last().unwrap().as_str()));
-                let stage = &stages.last().unwrap();
-
-                let mut post_chosen_constraints =
-                    get_boost_contributions(&combat, &boosts, problem.boost_damage, turn);
-                let mut pre_chosen_constraints = post_chosen_constraints.clone();
-                // Remove the contribution of the chosen hero for this turn and add what it
-                // was on the previous turn.
-                pre_chosen_constraints.pop();
-                pre_chosen_constraints
-                    .push(turn as i32 * problem.boost_damage * &chosen_hero_boost.rep);
-
-                let max_total_damage = (turn as i32 + 1) * max_boost_damage + combat.chosen_damage;
-                // If the stage has been chosen, we need an upper bound on the damage done
-                // before the chosen hero and a lower bound on the damage done by the chosen
-                // hero themselves.
-                // We add large constants to ensure that when the stage is not selected,
-                // these constraints have no effect.
-                pre_chosen_constraints.push(max_total_damage * *stage);
-                post_chosen_constraints.push(-max_total_damage * *stage);
-
-                // -1 to be exclusive, we don't want the monster to die yet.
-                lp += lp_sum(&pre_chosen_constraints).le(remaining_health + max_total_damage - 1);
-                lp += lp_sum(&post_chosen_constraints)
-                    .ge(remaining_health - combat.chosen_damage - max_total_damage);
-            }
+// Rounds towards positive infinity; `b` must be positive.
+pub(crate) fn ceil_div(a: i64, b: i64) -> i64 {
+    if a % b > 0 {
+        a / b + 1
+    } else {
+        a / b
+    }
+}
 
-            remaining_health -= combat.chosen_damage + combat.late_damage;
+pub(crate) fn to_i64(value: usize, what: &str) -> Result<i64, MonsterError> {
+    i64::try_from(value).ok().or_overflow(what)
+}
 
-            if remaining_health <= 0 {
-                break;
-            }
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SolveStatus {
+    Solved,
+    Infeasible,
+    // The solver hit the time limit, or gave up without an answer for
+    // another reason, such as the node limit. Any boosts are the best found
+    // so far, and may be far from the objective's best.
+    TimedOut,
+    Unknown,
+}
+
+impl fmt::Display for SolveStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            SolveStatus::Solved => "solved",
+            SolveStatus::Infeasible => "infeasible",
+            SolveStatus::TimedOut => "timed_out",
+            SolveStatus::Unknown => "unknown",
+        })
+    }
+}
+
+// What to optimise for among the boost vectors that work.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Objective {
+    // Whatever the backend finds first.
+    #[default]
+    AnyFeasible,
+    FewestBoosts,
+    EarliestKill,
+    // Boosts concentrated on as few heroes as possible.
+    FewestHeroes,
+}
+
+impl Objective {
+    pub fn all() -> &'static [Objective] {
+        &[
+            Objective::AnyFeasible,
+            Objective::FewestBoosts,
+            Objective::EarliestKill,
+            Objective::FewestHeroes,
+        ]
+    }
+}
+
+impl fmt::Display for Objective {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Objective::AnyFeasible => "any",
+            Objective::FewestBoosts => "fewest_boosts",
+            Objective::EarliestKill => "earliest_kill",
+            Objective::FewestHeroes => "fewest_heroes",
+        })
+    }
+}
+
+impl FromStr for Objective {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Objective, String> {
+        Objective::all()
+            .iter()
+            .find(|objective| objective.to_string() == s)
+            .cloned()
+            .ok_or_else(|| format!("unknown objective: {}", s))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ModelSize {
+    pub variables: usize,
+    pub constraints: usize,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct SolveOptions {
+    // Where to write the LP model, for backends that build one.
+    pub lp_file: Option<PathBuf>,
+    pub objective: Objective,
+    // Limits on the search. A search that hits one stops with the best
+    // boosts found so far.
+    pub time_limit: Option<Duration>,
+    pub node_limit: Option<u64>,
+}
+
+impl SolveOptions {
+    pub fn has_limits(&self) -> bool {
+        self.time_limit.is_some() || self.node_limit.is_some()
+    }
+
+    // The status to stop with, if a search that has run for `elapsed` and
+    // visited `nodes` nodes has hit a limit.
+    pub(crate) fn limit_reached(&self, elapsed: Duration, nodes: u64) -> Option<SolveStatus> {
+        if self.time_limit.is_some_and(|limit| elapsed >= limit) {
+            Some(SolveStatus::TimedOut)
+        } else if self.node_limit.is_some_and(|limit| nodes >= limit) {
+            Some(SolveStatus::Unknown)
+        } else {
+            None
         }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SolveReport {
+    pub backend: &'static str,
+    pub objective: Objective,
+    pub status: SolveStatus,
+    // The turn on which the chosen hero lands the killing blow.
+    pub turn: Option<usize>,
+    pub boosts: Option<Vec<usize>>,
+    // Like `boosts`, but for the health boosts. Only filled in for problems
+    // that have them.
+    pub health_boosts: Option<Vec<usize>>,
+    // Only filled in by backends that build an explicit model.
+    pub model_size: Option<ModelSize>,
+    // Anything the backend has to add about the answer, such as why a
+    // search that stopped early has nothing to show for it.
+    pub note: Option<String>,
+    pub elapsed: Duration,
+}
+
+impl SolveReport {
+    // The backend name, objective and timing are filled in by `solve_with`.
+    pub fn solved(turn: usize, boosts: Vec<usize>) -> SolveReport {
+        SolveReport {
+            backend: "",
+            objective: Objective::default(),
+            status: SolveStatus::Solved,
+            turn: Some(turn),
+            boosts: Some(boosts),
+            health_boosts: None,
+            model_size: None,
+            note: None,
+            elapsed: Duration::default(),
+        }
+    }
 
-        // At no stage can the monster be slain.
-        if stages.is_empty() {
-            return None;
+    pub fn infeasible() -> SolveReport {
+        SolveReport {
+            backend: "",
+            objective: Objective::default(),
+            status: SolveStatus::Infeasible,
+            turn: None,
+            boosts: None,
+            health_boosts: None,
+            model_size: None,
+            note: None,
+            elapsed: Duration::default(),
         }
+    }
 
-        lp += lp_sum(&stages);
+    pub fn with_health_boosts(self, health_boosts: Vec<usize>) -> SolveReport {
+        SolveReport {
+            health_boosts: Some(health_boosts),
+            ..self
+        }
     }
 
-    lp.write_lp("test.lp").unwrap();
-    let solver = CbcSolver::new();
-    let (status, results) = solver.run(&lp).unwrap();
-    println!("Result: {:?} {:?}", status, results);
-    match status {
-        Status::Infeasible => None,
-        Status::Optimal => {
-            println!("Stages: {:?}", stage_labels);
-            if stage_labels.iter().all(|label| *results.get(label).unwrap_or(&0.0) == 0.0) {
-                return None;
-            }
-            let mut result = Vec::new();
-            for boost in &boosts {
-                for _ in 0..(*results.get(&boost.label).unwrap_or(&0.0) as usize) {
-                    result.push(boost.index)
-                }
-            }
-            Some(result)
+    // For a search that stopped early, with or without boosts.
+    pub fn with_status(self, status: SolveStatus) -> SolveReport {
+        SolveReport { status, ..self }
+    }
+
+    pub fn with_note(self, note: &str) -> SolveReport {
+        SolveReport {
+            note: Some(note.to_string()),
+            ..self
         }
-        _ => {
-            panic!("Unexpected status: {:?}", status);
+    }
+
+    pub fn with_model_size(self, model_size: ModelSize) -> SolveReport {
+        SolveReport {
+            model_size: Some(model_size),
+            ..self
         }
     }
 }
 
-fn build_combat(problem: &Problem) -> Combat {
-    let early_heroes = &problem.heroes[0..problem.chosen_hero];
-    let chosen_hero = &problem.heroes[problem.chosen_hero];
-    let late_heroes = &problem.heroes[problem.chosen_hero + 1..problem.heroes.len()];
+// Finds boosts that let the chosen hero land the killing blow, using the
+// default backend.
+pub fn solve(problem: &Problem) -> Result<SolveReport, MonsterError> {
+    solve_with(problem, &Backend::default(), &SolveOptions::default())
+}
 
-    let mut phases = Vec::with_capacity(problem.chosen_hero + 1);
-    let cumulative_damage = build_cumulative_damage(early_heroes);
-    let cumulative_health = cumulative_sum(early_heroes.iter().map(|x| x.health));
+pub fn solve_with(
+    problem: &Problem,
+    solver: &dyn MonsterSolver,
+    options: &SolveOptions,
+) -> Result<SolveReport, MonsterError> {
+    problem.validate()?;
+    problem.reject_random_damage(solver.name())?;
+    let start = Instant::now();
+    let report = solver.solve(problem, options)?;
+    Ok(SolveReport {
+        backend: solver.name(),
+        objective: options.objective,
+        elapsed: start.elapsed(),
+        ..report
+    })
+}
+
+pub(crate) fn build_combat(problem: &Problem) -> Result<Combat, MonsterError> {
+    let attack_turns = attack_turns(&problem.heroes, &problem.rules)?;
+    combat_for(problem, &attack_turns, problem.chosen_hero)
+}
 
-    for i in 0..early_heroes.len() {
-        phases.push(CombatPhase {
-            end_stage: cumulative_health[i],
-            early_damage: cumulative_damage[i],
-        });
+// The combat as seen by the given hero. Who dies when doesn't depend on who
+// is chosen, so the attack turns can be shared between heroes.
+pub(crate) fn combat_for(
+    problem: &Problem,
+    attack_turns: &[usize],
+    chosen_hero: usize,
+) -> Result<Combat, MonsterError> {
+    let chosen_turns = attack_turns[chosen_hero];
+    let mut ranks = vec![0; problem.heroes.len()];
+    for (rank, hero) in attack_order(&problem.heroes).into_iter().enumerate() {
+        ranks[hero] = rank;
     }
-    phases.push(CombatPhase {
-        end_stage: cumulative_health.last().unwrap_or(&0) + chosen_hero.health,
-        early_damage: 0,
-    });
+    let chosen_rank = ranks[chosen_hero];
 
-    Combat {
+    // A new phase starts whenever someone dies while the chosen hero lives.
+    let mut ends: Vec<usize> = attack_turns
+        .iter()
+        .copied()
+        .filter(|&turns| turns < chosen_turns)
+        .collect();
+    ends.push(chosen_turns);
+    ends.sort_unstable();
+    ends.dedup();
+
+    let phases = ends
+        .into_iter()
+        .map(|end_stage| {
+            // Everyone attacks from the first turn, so whoever is still alive
+            // at the end of the phase was alive throughout.
+            let damage = |early: bool| {
+                (0..problem.heroes.len())
+                    .filter(|&hero| hero != chosen_hero && (ranks[hero] < chosen_rank) == early)
+                    .filter(|&hero| attack_turns[hero] >= end_stage)
+                    .try_fold(0i64, |total, hero| {
+                        total.checked_add(problem.heroes[hero].damage)
+                    })
+                    .or_overflow("the damage of the heroes together")
+            };
+            Ok(CombatPhase {
+                end_stage,
+                early_damage: damage(true)?,
+                late_damage: damage(false)?,
+            })
+        })
+        .collect::<Result<_, MonsterError>>()?;
+
+    let capped = problem.boost_cap(chosen_hero)
+        < problem
+            .max_boosts
+            .saturating_add(problem.rules.max_health_boosts);
+    Ok(Combat {
         phases,
-        late_damage: late_heroes.iter().map(|x| x.damage).sum(),
-        chosen_damage: problem.heroes[problem.chosen_hero].damage,
-    }
+        boostable: attack_turns
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|&(hero, turns)| capped || ranks[hero] <= chosen_rank || turns < chosen_turns)
+            .collect(),
+        chosen_hero,
+        chosen_damage: problem.heroes[chosen_hero].damage,
+        caps: (0..problem.heroes.len())
+            .map(|hero| problem.boost_cap(hero))
+            .collect(),
+        ranks,
+    })
 }
 
-fn cumulative_sum<T: Add<Output = T> + Default + Copy, I>(xs: I) -> Vec<T>
-where
-    I: Iterator<Item = T>,
-{
-    let mut total = T::default();
-    let mut sums = Vec::new();
-    for x in xs {
-        total = total.add(x);
-        sums.push(total);
+// The combat as seen by the given hero once the health boosts, given as a
+// count per hero, have been applied. They only change who dies when, and
+// count against each hero's cap.
+pub(crate) fn combat_with_health(
+    problem: &Problem,
+    chosen_hero: usize,
+    health_boosts: &[usize],
+) -> Result<Combat, MonsterError> {
+    let heroes = problem
+        .heroes
+        .iter()
+        .zip(health_boosts)
+        .enumerate()
+        .map(|(i, (hero, &count))| {
+            let health = problem
+                .rules
+                .health_boost
+                .checked_mul(count)
+                .and_then(|extra| extra.checked_add(hero.health))
+                .ok_or_else(|| MonsterError::Overflow(format!("health of hero {}", i)))?;
+            Ok(Hero {
+                health,
+                ..hero.clone()
+            })
+        })
+        .collect::<Result<Vec<_>, MonsterError>>()?;
+    let attack_turns = attack_turns(&heroes, &problem.rules)?;
+    let mut combat = combat_for(problem, &attack_turns, chosen_hero)?;
+    for (cap, &count) in combat.caps.iter_mut().zip(health_boosts) {
+        *cap = cap.saturating_sub(count);
     }
-    sums
+    Ok(combat)
 }
 
-fn build_cumulative_damage(heroes: &[Hero]) -> Vec<i32> {
-    let mut cumulative_damage = cumulative_sum(heroes.iter().map(|x| x.damage).rev());
-    cumulative_damage.reverse();
-    cumulative_damage
+// The number of turns each hero attacks for. Heroes attack from the first
+// turn until they die, so this is also the first turn they miss.
+pub(crate) fn attack_turns(heroes: &[Hero], rules: &Rules) -> Result<Vec<usize>, MonsterError> {
+    let attack = &rules.monster_attack;
+    let mut next_front_turn: usize = 0;
+    heroes
+        .iter()
+        .map(|hero| {
+            let first_hit = match attack.target {
+                Target::Front => next_front_turn,
+                Target::All => 0,
+            };
+            // At least one hit is needed, so this is the turn of the last.
+            let death = first_hit
+                .checked_add(hits_to_kill(attack, first_hit, hero.health) - 1)
+                .or_overflow("the turn a hero dies")?;
+            next_front_turn = death.checked_add(1).or_overflow("the turn a hero dies")?;
+            // When the monster goes first, a hero dies before attacking on
+            // the turn of their death.
+            Ok(match rules.turn_order {
+                TurnOrder::HeroesFirst => next_front_turn,
+                TurnOrder::MonsterFirst => death,
+            })
+        })
+        .collect()
 }
 
-fn get_boost_contributions(
-    combat: &Combat,
-    boosts: &[DynVariable],
-    boost_damage: i32,
-    turn: usize,
-) -> Vec<LpExpression> {
-    combat
-        .phases
+// What is left of each hero's health once the monster has attacked `attacks`
+// times, from the first turn on, or zero for those who died.
+pub(crate) fn health_after(heroes: &[Hero], attack: &MonsterAttack, attacks: usize) -> Vec<usize> {
+    let mut next_front_turn: usize = 0;
+    heroes
         .iter()
-        .zip(boosts.iter())
-        .map(|(phase, boost)| {
-            let damage = if phase.end_stage <= turn {
-                phase.end_stage as i32 * boost_damage
-            } else {
-                (turn as i32 + 1) * boost_damage
+        .map(|hero| {
+            let first_hit = match attack.target {
+                Target::Front => next_front_turn,
+                Target::All => 0,
             };
-            damage * &boost.rep
+            let hits = attacks.saturating_sub(first_hit);
+            let taken = damage_taken(attack, first_hit as u128, hits as u128);
+            if taken >= hero.health as u128 {
+                // They died within the attacks, so this is at most `attacks`.
+                next_front_turn = first_hit + hits_to_kill(attack, first_hit, hero.health);
+                0
+            } else {
+                next_front_turn = attacks;
+                hero.health - taken as usize
+            }
         })
         .collect()
 }
 
-#[allow(clippy::identity_op)]
-fn set_max_boosts(lp: &mut LpProblem, boosts: &[DynVariable], max_boosts: usize) {
-    // Yes, the 1* looks weird, but I can't find a different way to convert an LpInteger
-    // to an LpExpression without borrowing it.
-    let boost_exprs: Vec<LpExpression> = boosts.iter().map(|boost| 1 * &boost.rep).collect();
-    *lp += lp_sum(&boost_exprs).le(max_boosts as i32);
+// How many hits on consecutive turns, starting on `first_hit`, it takes to
+// deal `health` damage.
+fn hits_to_kill(attack: &MonsterAttack, first_hit: usize, health: usize) -> usize {
+    let health = health as u128;
+    // The monster deals at least one damage per hit, or grows by at least
+    // one per turn, so health + 1 hits always do.
+    let (mut lo, mut hi) = (1, health + 1);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if damage_taken(attack, first_hit as u128, mid) >= health {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    lo as usize
+}
+
+// The damage of `hits` hits on consecutive turns, starting on `first_hit`.
+// Saturating is fine, as it is only ever compared against a health.
+fn damage_taken(attack: &MonsterAttack, first_hit: u128, hits: u128) -> u128 {
+    if hits == 0 {
+        return 0;
+    }
+    let turns = hits
+        .saturating_mul(first_hit)
+        .saturating_add(hits.saturating_mul(hits - 1) / 2);
+    hits.saturating_mul(attack.damage as u128)
+        .saturating_add((attack.growth as u128).saturating_mul(turns))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use problem::Rules;
+
+    #[test]
+    fn parse_objective_names() {
+        for objective in Objective::all() {
+            assert_eq!(Ok(*objective), objective.to_string().parse());
+        }
+        assert!("cheapest".parse::<Objective>().is_err());
+    }
+
+    #[test]
+    fn solve_rejects_invalid_problem() {
+        match solve(&Problem {
+            monster_health: 10,
+            heroes: vec![Hero::new(10, 1)],
+            chosen_hero: 1,
+            boost_damage: 1,
+            max_boosts: 1,
+            rules: Rules::default(),
+        }) {
+            Err(MonsterError::InvalidProblem(_)) => {}
+            other => panic!("Expected an invalid problem, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn window_candidate_turns() {
+        let window = PhaseWindow {
+            start: 10,
+            end: 20,
+            health: 100,
+            turn_damage: 10,
+        };
+        // Without boosts the chosen hero needs the monster at 5 health or
+        // less, which first happens on turn 20; the monster is dead by then.
+        assert_eq!(Ok(10..10), window.candidate_turns(5, 0));
+        // 4 extra damage per turn gives (turn + 1) * 4 >= 95 - (turn - 10) * 10.
+        assert_eq!(Ok(14..20), window.candidate_turns(5, 4));
+        assert_eq!(Ok(10..20), window.candidate_turns(100, 0));
+    }
+
+    #[test]
+    fn window_monster_dies() {
+        let window = PhaseWindow {
+            start: 0,
+            end: 20,
+            health: 35,
+            turn_damage: 10,
+        };
+        assert_eq!(Ok(0..4), window.candidate_turns(100, 0));
+    }
+
+    #[test]
+    fn various_ceil_div() {
+        assert_eq!(0, ceil_div(0, 3));
+        assert_eq!(1, ceil_div(1, 3));
+        assert_eq!(1, ceil_div(3, 3));
+        assert_eq!(2, ceil_div(4, 3));
+        assert_eq!(-1, ceil_div(-4, 3));
+        assert_eq!(1 << 62, ceil_div(i64::MAX, 2));
+    }
 
     #[test]
     fn build_simple_combat() {
         assert_eq!(
-            Combat {
+            Ok(Combat {
                 phases: vec![
                     CombatPhase {
                         end_stage: 10,
-                        early_damage: 30
+                        early_damage: 30,
+                        late_damage: 30
                     },
                     CombatPhase {
                         end_stage: 25,
-                        early_damage: 20
+                        early_damage: 20,
+                        late_damage: 30
                     },
                     CombatPhase {
                         end_stage: 45,
-                        early_damage: 0
+                        early_damage: 0,
+                        late_damage: 30
                     }
                 ],
+                boostable: vec![(0, 10), (1, 25), (2, 45)],
+                chosen_hero: 2,
                 chosen_damage: 50,
-                late_damage: 30,
-            },
+                caps: vec![usize::MAX; 4],
+                ranks: vec![0, 1, 2, 3],
+            }),
             build_combat(&Problem {
                 monster_health: 100,
                 heroes: vec![
-                    Hero {
-                        health: 10,
-                        damage: 10,
-                    },
-                    Hero {
-                        health: 15,
-                        damage: 20,
-                    },
-                    Hero {
-                        health: 20,
-                        damage: 50,
+                    Hero::new(10, 10),
+                    Hero::new(15, 20),
+                    Hero::new(20, 50),
+                    Hero::new(10, 30),
+                ],
+                chosen_hero: 2,
+                boost_damage: 0,
+                max_boosts: 0,
+                rules: Rules::default(),
+            })
+        );
+    }
+
+    #[test]
+    fn build_monster_first_combat() {
+        assert_eq!(
+            Ok(Combat {
+                phases: vec![
+                    CombatPhase {
+                        end_stage: 0,
+                        early_damage: 30,
+                        late_damage: 0
                     },
-                    Hero {
-                        health: 10,
-                        damage: 30,
+                    CombatPhase {
+                        end_stage: 15,
+                        early_damage: 20,
+                        late_damage: 0
                     },
+                    CombatPhase {
+                        end_stage: 35,
+                        early_damage: 0,
+                        late_damage: 0
+                    }
                 ],
+                boostable: vec![(0, 0), (1, 15), (2, 35)],
+                chosen_hero: 2,
+                chosen_damage: 50,
+                caps: vec![usize::MAX; 3],
+                ranks: vec![0, 1, 2],
+            }),
+            build_combat(&Problem {
+                monster_health: 100,
+                heroes: vec![Hero::new(1, 10), Hero::new(15, 20), Hero::new(20, 50),],
                 chosen_hero: 2,
                 boost_damage: 0,
                 max_boosts: 0,
+                rules: Rules {
+                    turn_order: TurnOrder::MonsterFirst,
+                    ..Rules::default()
+                },
             })
         );
     }
 
     #[test]
-    fn various_build_cumulative_damage() {
-        assert!(build_cumulative_damage(&[]).is_empty());
+    fn build_combat_by_speed() {
+        // The hero at the back is fastest, so their damage comes before the
+        // chosen hero's, and they are worth boosting even though they live
+        // the longest.
+        let mut problem = Problem {
+            monster_health: 100,
+            heroes: vec![
+                Hero::new(10, 10),
+                Hero::new(15, 20),
+                Hero::new(20, 50),
+                Hero::new(10, 30),
+            ],
+            chosen_hero: 2,
+            boost_damage: 0,
+            max_boosts: 0,
+            rules: Rules::default(),
+        };
+        problem.heroes[3].speed = 1;
         assert_eq!(
-            vec![10],
-            build_cumulative_damage(&[Hero {
-                health: 10,
-                damage: 10
-            }])
+            Ok(Combat {
+                phases: vec![
+                    CombatPhase {
+                        end_stage: 10,
+                        early_damage: 60,
+                        late_damage: 0
+                    },
+                    CombatPhase {
+                        end_stage: 25,
+                        early_damage: 50,
+                        late_damage: 0
+                    },
+                    CombatPhase {
+                        end_stage: 45,
+                        early_damage: 30,
+                        late_damage: 0
+                    }
+                ],
+                boostable: vec![(0, 10), (1, 25), (2, 45), (3, 55)],
+                chosen_hero: 2,
+                chosen_damage: 50,
+                caps: vec![usize::MAX; 4],
+                ranks: vec![1, 2, 3, 0],
+            }),
+            build_combat(&problem)
         );
+    }
+
+    #[test]
+    fn build_all_target_combat() {
+        // The third hero dies first, before the chosen hero does.
         assert_eq!(
-            vec![10, 3],
-            build_cumulative_damage(&[
-                Hero {
-                    health: 10,
-                    damage: 7
+            Ok(Combat {
+                phases: vec![
+                    CombatPhase {
+                        end_stage: 1,
+                        early_damage: 10,
+                        late_damage: 70
+                    },
+                    CombatPhase {
+                        end_stage: 2,
+                        early_damage: 10,
+                        late_damage: 40
+                    }
+                ],
+                boostable: vec![(0, 5), (1, 2), (2, 1)],
+                chosen_hero: 1,
+                chosen_damage: 20,
+                caps: vec![usize::MAX; 4],
+                ranks: vec![0, 1, 2, 3],
+            }),
+            build_combat(&Problem {
+                monster_health: 100,
+                heroes: vec![
+                    Hero::new(5, 10),
+                    Hero::new(2, 20),
+                    Hero::new(1, 30),
+                    Hero::new(3, 40)
+                ],
+                chosen_hero: 1,
+                boost_damage: 0,
+                max_boosts: 0,
+                rules: Rules {
+                    monster_attack: MonsterAttack {
+                        damage: 1,
+                        target: Target::All,
+                        growth: 0,
+                    },
+                    ..Rules::default()
                 },
-                Hero {
-                    health: 10,
-                    damage: 3
-                }
-            ])
+            })
         );
+    }
+
+    #[test]
+    fn build_combat_with_health() {
+        // Two more health let the front hero take three hits instead of one.
+        let mut problem = Problem {
+            monster_health: 100,
+            heroes: vec![Hero::new(1, 10), Hero::new(5, 20)],
+            chosen_hero: 1,
+            boost_damage: 0,
+            max_boosts: 0,
+            rules: Rules::default(),
+        };
+        problem.rules.health_boost = 2;
+        problem.rules.max_health_boosts = 1;
+        problem.rules.boost_caps = vec![3];
         assert_eq!(
-            vec![15, 8, 5],
-            build_cumulative_damage(&[
-                Hero {
-                    health: 10,
-                    damage: 7
-                },
-                Hero {
-                    health: 10,
-                    damage: 3
-                },
-                Hero {
-                    health: 10,
-                    damage: 5
-                }
-            ])
+            Ok(Combat {
+                phases: vec![
+                    CombatPhase {
+                        end_stage: 3,
+                        early_damage: 10,
+                        late_damage: 0
+                    },
+                    CombatPhase {
+                        end_stage: 8,
+                        early_damage: 0,
+                        late_damage: 0
+                    }
+                ],
+                boostable: vec![(0, 3), (1, 8)],
+                chosen_hero: 1,
+                chosen_damage: 20,
+                caps: vec![2, usize::MAX],
+                ranks: vec![0, 1],
+            }),
+            combat_with_health(&problem, 1, &[1, 0])
         );
     }
 
     #[test]
-    fn various_cumulative_sum() {
-        assert_eq!(vec![10], cumulative_sum([10].iter().map(|&x| x)));
-        assert_eq!(vec![5, 8], cumulative_sum([5, 3].iter().map(|&x| x)));
+    fn growing_attack_turns() {
+        let mut problem = Problem {
+            monster_health: 100,
+            heroes: vec![Hero::new(3, 1), Hero::new(10, 1), Hero::new(1, 1)],
+            chosen_hero: 0,
+            boost_damage: 0,
+            max_boosts: 0,
+            rules: Rules {
+                monster_attack: MonsterAttack {
+                    damage: 2,
+                    target: Target::Front,
+                    growth: 1,
+                },
+                ..Rules::default()
+            },
+        };
+        assert_eq!(
+            Ok(vec![2, 5, 6]),
+            attack_turns(&problem.heroes, &problem.rules)
+        );
+        problem.rules.turn_order = TurnOrder::MonsterFirst;
+        assert_eq!(
+            Ok(vec![1, 4, 5]),
+            attack_turns(&problem.heroes, &problem.rules)
+        );
+        problem.rules.monster_attack.target = Target::All;
         assert_eq!(
-            vec![5, 12, 15],
-            cumulative_sum([5, 7, 3].iter().map(|&x| x))
+            Ok(vec![1, 3, 0]),
+            attack_turns(&problem.heroes, &problem.rules)
         );
     }
+
+    #[test]
+    fn health_after_attacks() {
+        let heroes = vec![Hero::new(3, 1), Hero::new(5, 1)];
+        let mut attack = MonsterAttack {
+            damage: 2,
+            target: Target::Front,
+            growth: 0,
+        };
+        assert_eq!(vec![3, 5], health_after(&heroes, &attack, 0));
+        assert_eq!(vec![1, 5], health_after(&heroes, &attack, 1));
+        assert_eq!(vec![0, 3], health_after(&heroes, &attack, 3));
+        assert_eq!(vec![0, 0], health_after(&heroes, &attack, 10));
+        // Hits of 1, 2 and 3.
+        attack.damage = 1;
+        attack.growth = 1;
+        assert_eq!(vec![0, 2], health_after(&heroes, &attack, 3));
+        attack.growth = 0;
+        attack.target = Target::All;
+        assert_eq!(vec![1, 3], health_after(&heroes, &attack, 2));
+    }
+
+    #[test]
+    fn hits_to_kill_huge_health() {
+        let attack = MonsterAttack::default();
+        assert_eq!(usize::MAX, hits_to_kill(&attack, 0, usize::MAX));
+        let attack = MonsterAttack {
+            damage: 0,
+            target: Target::Front,
+            growth: 1,
+        };
+        // 0 + 1 + 2 + 3 + 4 = 10
+        assert_eq!(5, hits_to_kill(&attack, 0, 10));
+        assert_eq!(2, hits_to_kill(&attack, usize::MAX - 1, usize::MAX));
+    }
 }
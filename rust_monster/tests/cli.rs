@@ -0,0 +1,34 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+// Runs the solver binary on the shipped example problem.
+fn solve(args: &[&str]) -> Output {
+    let problem = Path::new(env!("CARGO_MANIFEST_DIR")).join("problems/gist.txt");
+    Command::new(env!("CARGO_BIN_EXE_rust_monster_solver"))
+        .arg("solve")
+        .args(args)
+        .arg(problem)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn default_backend_takes_limits() {
+    let output = solve(&["--time-limit", "5"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(Some(0), output.status.code(), "{}", stdout);
+    assert!(stdout.contains("status: solved\n"), "{}", stdout);
+    assert!(stdout.contains("backend: native\n"), "{}", stdout);
+    assert!(stdout.contains("valid: true\n"), "{}", stdout);
+}
+
+#[test]
+fn node_limit_without_a_solution() {
+    let output = solve(&["--node-limit", "0"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(Some(1), output.status.code(), "{}", stdout);
+    assert!(stdout.contains("status: unknown\n"), "{}", stdout);
+    assert!(stdout.contains("boosts: none\n"), "{}", stdout);
+    // Only a search that ran to the end can tell why there is no solution.
+    assert!(!stdout.contains("infeasibility"), "{}", stdout);
+}
@@ -1,25 +1,61 @@
 extern crate rust_monster;
-use rust_monster::problem::{Hero, Problem};
-use rust_monster::simulation::solution_is_valid;
-use rust_monster::solution::solve;
+use rust_monster::backend::{solve_brute_force, Backend, CbcBackend, MonsterSolver};
+use rust_monster::batch::{solve_batch, BatchItem};
+use rust_monster::campaign::{
+    simulate_campaign, solve_campaign, BoostBudget, Campaign, CampaignPlan, Fight,
+};
+use rust_monster::enumerate::{count_distributions, distributions, Count};
+use rust_monster::error::MonsterError;
+use rust_monster::explain::{explain_infeasibility, Infeasibility, Reason};
+use rust_monster::generate::{Generator, GeneratorConfig};
+use rust_monster::monte_carlo::estimate_win_probability;
+use rust_monster::problem::{Hero, MonsterAttack, Problem, Rules, Target, TurnOrder};
+use rust_monster::reachability::reachability;
+use rust_monster::schedule::solve_schedule;
+use rust_monster::sensitivity::{min_boost_damage, min_max_boosts};
+use rust_monster::simulation::{
+    schedule_is_valid, simulate, simulate_schedule, solution_is_valid,
+    solution_is_valid_with_health,
+};
+use rust_monster::solution::{solve_with, Objective, SolveOptions, SolveReport, SolveStatus};
+
+// CBC is only checked where the binary is installed.
+fn backends() -> Vec<Backend> {
+    let mut backends = vec![Backend::Native];
+    if CbcBackend::is_available() {
+        backends.push(Backend::Cbc);
+    }
+    backends
+}
 
 fn assert_solveable(problem: &Problem) {
-    match solve(problem) {
-        Some(solution) => {
-            if !solution_is_valid(problem, &solution) {
-                panic!("Invalid solution: {:?}", solution);
+    for backend in backends() {
+        match solve_with(problem, &backend, &SolveOptions::default())
+            .unwrap()
+            .boosts
+        {
+            Some(solution) => {
+                if !solution_is_valid(problem, &solution).unwrap() {
+                    panic!("Invalid solution from {}: {:?}", backend.name(), solution);
+                }
             }
+            None => panic!("Expected {} to find a solution.", backend.name()),
         }
-        None => panic!("Expected to find a solution."),
     }
 }
 
 fn assert_not_solveable(problem: &Problem) {
-    match solve(problem) {
-        Some(solution) => {
-            panic!("Expected unsolvable problem, got solution {:?}", solution);
+    for backend in backends() {
+        if let Some(solution) = solve_with(problem, &backend, &SolveOptions::default())
+            .unwrap()
+            .boosts
+        {
+            panic!(
+                "Expected unsolvable problem, got solution {:?} from {}",
+                solution,
+                backend.name()
+            );
         }
-        None => {}
     }
 }
 
@@ -27,13 +63,11 @@ fn assert_not_solveable(problem: &Problem) {
 fn war_of_attrition() {
     assert_solveable(&Problem {
         monster_health: 100,
-        heroes: vec![Hero {
-            health: 20,
-            damage: 1,
-        }],
+        heroes: vec![Hero::new(20, 1)],
         chosen_hero: 0,
         boost_damage: 6,
         max_boosts: 1,
+        rules: Rules::default(),
     });
 }
 
@@ -41,13 +75,11 @@ fn war_of_attrition() {
 fn barely_enough() {
     assert_solveable(&Problem {
         monster_health: 10,
-        heroes: vec![Hero {
-            health: 2,
-            damage: 4,
-        }],
+        heroes: vec![Hero::new(2, 4)],
         chosen_hero: 0,
         boost_damage: 1,
         max_boosts: 1,
+        rules: Rules::default(),
     });
 }
 
@@ -55,13 +87,11 @@ fn barely_enough() {
 fn looser_bounds() {
     assert_solveable(&Problem {
         monster_health: 10,
-        heroes: vec![Hero {
-            health: 3,
-            damage: 4,
-        }],
+        heroes: vec![Hero::new(3, 4)],
         chosen_hero: 0,
         boost_damage: 1,
         max_boosts: 1,
+        rules: Rules::default(),
     });
 }
 
@@ -69,19 +99,11 @@ fn looser_bounds() {
 fn the_dynamic_duo() {
     assert_solveable(&Problem {
         monster_health: 200,
-        heroes: vec![
-            Hero {
-                health: 2,
-                damage: 5,
-            },
-            Hero {
-                health: 1,
-                damage: 20,
-            },
-        ],
+        heroes: vec![Hero::new(2, 5), Hero::new(1, 20)],
         chosen_hero: 1,
         boost_damage: 10,
         max_boosts: 5,
+        rules: Rules::default(),
     });
 }
 
@@ -89,23 +111,11 @@ fn the_dynamic_duo() {
 fn woefully_underleveled() {
     assert_not_solveable(&Problem {
         monster_health: 100,
-        heroes: vec![
-            Hero {
-                health: 1,
-                damage: 1,
-            },
-            Hero {
-                health: 1,
-                damage: 2,
-            },
-            Hero {
-                health: 1,
-                damage: 3,
-            },
-        ],
+        heroes: vec![Hero::new(1, 1), Hero::new(1, 2), Hero::new(1, 3)],
         chosen_hero: 0,
         boost_damage: 5,
         max_boosts: 5,
+        rules: Rules::default(),
     });
 }
 
@@ -114,26 +124,15 @@ fn unfortunate_overkill() {
     assert_not_solveable(&Problem {
         monster_health: 200,
         heroes: vec![
-            Hero {
-                health: 2,
-                damage: 1,
-            },
-            Hero {
-                health: 1,
-                damage: 5,
-            },
-            Hero {
-                health: 2,
-                damage: 100,
-            },
-            Hero {
-                health: 1,
-                damage: 1,
-            },
+            Hero::new(2, 1),
+            Hero::new(1, 5),
+            Hero::new(2, 100),
+            Hero::new(1, 1),
         ],
         chosen_hero: 3,
         boost_damage: 5,
         max_boosts: 3,
+        rules: Rules::default(),
     });
 }
 
@@ -142,42 +141,19 @@ fn strength_in_numbers() {
     assert_solveable(&Problem {
         monster_health: 25,
         heroes: vec![
-            Hero {
-                health: 1,
-                damage: 1,
-            },
-            Hero {
-                health: 1,
-                damage: 1,
-            },
-            Hero {
-                health: 1,
-                damage: 1,
-            },
-            Hero {
-                health: 1,
-                damage: 1,
-            },
-            Hero {
-                health: 1,
-                damage: 1,
-            },
-            Hero {
-                health: 1,
-                damage: 1,
-            },
-            Hero {
-                health: 1,
-                damage: 1,
-            },
-            Hero {
-                health: 1,
-                damage: 1,
-            },
+            Hero::new(1, 1),
+            Hero::new(1, 1),
+            Hero::new(1, 1),
+            Hero::new(1, 1),
+            Hero::new(1, 1),
+            Hero::new(1, 1),
+            Hero::new(1, 1),
+            Hero::new(1, 1),
         ],
         chosen_hero: 7,
         boost_damage: 1,
         max_boosts: 2,
+        rules: Rules::default(),
     });
 }
 
@@ -186,82 +162,29 @@ fn healthy_heroes() {
     assert_solveable(&Problem {
         monster_health: 465,
         heroes: vec![
-            Hero {
-                health: 11,
-                damage: 15,
-            },
-            Hero {
-                health: 13,
-                damage: 10,
-            },
-            Hero {
-                health: 3,
-                damage: 19,
-            },
-            Hero {
-                health: 4,
-                damage: 24,
-            },
-            Hero {
-                health: 10,
-                damage: 14,
-            },
-            Hero {
-                health: 11,
-                damage: 30,
-            },
-            Hero {
-                health: 11,
-                damage: 23,
-            },
-            Hero {
-                health: 3,
-                damage: 23,
-            },
-            Hero {
-                health: 10,
-                damage: 4,
-            },
-            Hero {
-                health: 12,
-                damage: 32,
-            },
-            Hero {
-                health: 10,
-                damage: 12,
-            },
-            Hero {
-                health: 11,
-                damage: 20,
-            },
-            Hero {
-                health: 10,
-                damage: 9,
-            },
-            Hero {
-                health: 14,
-                damage: 8,
-            },
-            Hero {
-                health: 1,
-                damage: 12,
-            },
-            Hero {
-                health: 9,
-                damage: 25,
-            },
-            Hero {
-                health: 2,
-                damage: 30,
-            },
-            Hero {
-                health: 133,
-                damage: 1,
-            },
+            Hero::new(11, 15),
+            Hero::new(13, 10),
+            Hero::new(3, 19),
+            Hero::new(4, 24),
+            Hero::new(10, 14),
+            Hero::new(11, 30),
+            Hero::new(11, 23),
+            Hero::new(3, 23),
+            Hero::new(10, 4),
+            Hero::new(12, 32),
+            Hero::new(10, 12),
+            Hero::new(11, 20),
+            Hero::new(10, 9),
+            Hero::new(14, 8),
+            Hero::new(1, 12),
+            Hero::new(9, 25),
+            Hero::new(2, 30),
+            Hero::new(133, 1),
         ],
         chosen_hero: 5,
         boost_damage: 3,
         max_boosts: 9,
+        rules: Rules::default(),
     });
 }
 
@@ -270,86 +193,30 @@ fn large_battle() {
     assert_solveable(&Problem {
         monster_health: 1210,
         heroes: vec![
-            Hero {
-                health: 5,
-                damage: 19,
-            },
-            Hero {
-                health: 5,
-                damage: 28,
-            },
-            Hero {
-                health: 5,
-                damage: 24,
-            },
-            Hero {
-                health: 1,
-                damage: 29,
-            },
-            Hero {
-                health: 5,
-                damage: 17,
-            },
-            Hero {
-                health: 5,
-                damage: 28,
-            },
-            Hero {
-                health: 7,
-                damage: 30,
-            },
-            Hero {
-                health: 5,
-                damage: 10,
-            },
-            Hero {
-                health: 6,
-                damage: 15,
-            },
-            Hero {
-                health: 8,
-                damage: 29,
-            },
-            Hero {
-                health: 8,
-                damage: 13,
-            },
-            Hero {
-                health: 2,
-                damage: 25,
-            },
-            Hero {
-                health: 2,
-                damage: 5,
-            },
-            Hero {
-                health: 6,
-                damage: 5,
-            },
-            Hero {
-                health: 4,
-                damage: 8,
-            },
-            Hero {
-                health: 5,
-                damage: 6,
-            },
-            Hero {
-                health: 7,
-                damage: 7,
-            },
-            Hero {
-                health: 8,
-                damage: 25,
-            },
-            Hero {
-                health: 2,
-                damage: 12,
-            },
+            Hero::new(5, 19),
+            Hero::new(5, 28),
+            Hero::new(5, 24),
+            Hero::new(1, 29),
+            Hero::new(5, 17),
+            Hero::new(5, 28),
+            Hero::new(7, 30),
+            Hero::new(5, 10),
+            Hero::new(6, 15),
+            Hero::new(8, 29),
+            Hero::new(8, 13),
+            Hero::new(2, 25),
+            Hero::new(2, 5),
+            Hero::new(6, 5),
+            Hero::new(4, 8),
+            Hero::new(5, 6),
+            Hero::new(7, 7),
+            Hero::new(8, 25),
+            Hero::new(2, 12),
         ],
         chosen_hero: 11,
         boost_damage: 17,
         max_boosts: 8,
+        rules: Rules::default(),
     });
 }
 
@@ -358,82 +225,29 @@ fn current_year() {
     assert_solveable(&Problem {
         monster_health: 2020,
         heroes: vec![
-            Hero {
-                health: 1,
-                damage: 5,
-            },
-            Hero {
-                health: 2,
-                damage: 25,
-            },
-            Hero {
-                health: 2,
-                damage: 18,
-            },
-            Hero {
-                health: 1,
-                damage: 8,
-            },
-            Hero {
-                health: 2,
-                damage: 15,
-            },
-            Hero {
-                health: 1,
-                damage: 18,
-            },
-            Hero {
-                health: 1,
-                damage: 30,
-            },
-            Hero {
-                health: 1,
-                damage: 5,
-            },
-            Hero {
-                health: 2,
-                damage: 21,
-            },
-            Hero {
-                health: 1,
-                damage: 13,
-            },
-            Hero {
-                health: 2,
-                damage: 7,
-            },
-            Hero {
-                health: 1,
-                damage: 26,
-            },
-            Hero {
-                health: 1,
-                damage: 11,
-            },
-            Hero {
-                health: 2,
-                damage: 26,
-            },
-            Hero {
-                health: 1,
-                damage: 18,
-            },
-            Hero {
-                health: 2,
-                damage: 17,
-            },
-            Hero {
-                health: 1,
-                damage: 22,
-            },
-            Hero {
-                health: 2,
-                damage: 21,
-            },
+            Hero::new(1, 5),
+            Hero::new(2, 25),
+            Hero::new(2, 18),
+            Hero::new(1, 8),
+            Hero::new(2, 15),
+            Hero::new(1, 18),
+            Hero::new(1, 30),
+            Hero::new(1, 5),
+            Hero::new(2, 21),
+            Hero::new(1, 13),
+            Hero::new(2, 7),
+            Hero::new(1, 26),
+            Hero::new(1, 11),
+            Hero::new(2, 26),
+            Hero::new(1, 18),
+            Hero::new(2, 17),
+            Hero::new(1, 22),
+            Hero::new(2, 21),
         ],
         chosen_hero: 10,
         boost_damage: 12,
         max_boosts: 3,
+        rules: Rules::default(),
     });
 }
 
@@ -442,134 +256,42 @@ fn the_penultimate() {
     assert_solveable(&Problem {
         monster_health: 9089,
         heroes: vec![
-            Hero {
-                health: 2,
-                damage: 27,
-            },
-            Hero {
-                health: 2,
-                damage: 28,
-            },
-            Hero {
-                health: 1,
-                damage: 14,
-            },
-            Hero {
-                health: 1,
-                damage: 20,
-            },
-            Hero {
-                health: 1,
-                damage: 15,
-            },
-            Hero {
-                health: 2,
-                damage: 29,
-            },
-            Hero {
-                health: 2,
-                damage: 23,
-            },
-            Hero {
-                health: 1,
-                damage: 15,
-            },
-            Hero {
-                health: 1,
-                damage: 8,
-            },
-            Hero {
-                health: 1,
-                damage: 21,
-            },
-            Hero {
-                health: 1,
-                damage: 9,
-            },
-            Hero {
-                health: 2,
-                damage: 17,
-            },
-            Hero {
-                health: 1,
-                damage: 24,
-            },
-            Hero {
-                health: 1,
-                damage: 22,
-            },
-            Hero {
-                health: 2,
-                damage: 23,
-            },
-            Hero {
-                health: 1,
-                damage: 8,
-            },
-            Hero {
-                health: 1,
-                damage: 15,
-            },
-            Hero {
-                health: 1,
-                damage: 20,
-            },
-            Hero {
-                health: 2,
-                damage: 16,
-            },
-            Hero {
-                health: 1,
-                damage: 12,
-            },
-            Hero {
-                health: 2,
-                damage: 15,
-            },
-            Hero {
-                health: 1,
-                damage: 16,
-            },
-            Hero {
-                health: 2,
-                damage: 7,
-            },
-            Hero {
-                health: 1,
-                damage: 22,
-            },
-            Hero {
-                health: 1,
-                damage: 27,
-            },
-            Hero {
-                health: 1,
-                damage: 18,
-            },
-            Hero {
-                health: 1,
-                damage: 18,
-            },
-            Hero {
-                health: 1,
-                damage: 12,
-            },
-            Hero {
-                health: 2,
-                damage: 16,
-            },
-            Hero {
-                health: 2,
-                damage: 26,
-            },
-            Hero {
-                health: 1,
-                damage: 15,
-            },
+            Hero::new(2, 27),
+            Hero::new(2, 28),
+            Hero::new(1, 14),
+            Hero::new(1, 20),
+            Hero::new(1, 15),
+            Hero::new(2, 29),
+            Hero::new(2, 23),
+            Hero::new(1, 15),
+            Hero::new(1, 8),
+            Hero::new(1, 21),
+            Hero::new(1, 9),
+            Hero::new(2, 17),
+            Hero::new(1, 24),
+            Hero::new(1, 22),
+            Hero::new(2, 23),
+            Hero::new(1, 8),
+            Hero::new(1, 15),
+            Hero::new(1, 20),
+            Hero::new(2, 16),
+            Hero::new(1, 12),
+            Hero::new(2, 15),
+            Hero::new(1, 16),
+            Hero::new(2, 7),
+            Hero::new(1, 22),
+            Hero::new(1, 27),
+            Hero::new(1, 18),
+            Hero::new(1, 18),
+            Hero::new(1, 12),
+            Hero::new(2, 16),
+            Hero::new(2, 26),
+            Hero::new(1, 15),
         ],
         chosen_hero: 17,
         boost_damage: 20,
         max_boosts: 5,
+        rules: Rules::default(),
     });
 }
 
@@ -578,74 +300,27 @@ fn high_damage() {
     assert_not_solveable(&Problem {
         monster_health: 5630,
         heroes: vec![
-            Hero {
-                health: 1,
-                damage: 10,
-            },
-            Hero {
-                health: 2,
-                damage: 23,
-            },
-            Hero {
-                health: 2,
-                damage: 21,
-            },
-            Hero {
-                health: 2,
-                damage: 29,
-            },
-            Hero {
-                health: 2,
-                damage: 27,
-            },
-            Hero {
-                health: 1,
-                damage: 26,
-            },
-            Hero {
-                health: 1,
-                damage: 13,
-            },
-            Hero {
-                health: 2,
-                damage: 13,
-            },
-            Hero {
-                health: 1,
-                damage: 26,
-            },
-            Hero {
-                health: 2,
-                damage: 28,
-            },
-            Hero {
-                health: 1,
-                damage: 21,
-            },
-            Hero {
-                health: 1,
-                damage: 17,
-            },
-            Hero {
-                health: 1,
-                damage: 15,
-            },
-            Hero {
-                health: 1,
-                damage: 23,
-            },
-            Hero {
-                health: 1,
-                damage: 24,
-            },
-            Hero {
-                health: 2,
-                damage: 20,
-            },
+            Hero::new(1, 10),
+            Hero::new(2, 23),
+            Hero::new(2, 21),
+            Hero::new(2, 29),
+            Hero::new(2, 27),
+            Hero::new(1, 26),
+            Hero::new(1, 13),
+            Hero::new(2, 13),
+            Hero::new(1, 26),
+            Hero::new(2, 28),
+            Hero::new(1, 21),
+            Hero::new(1, 17),
+            Hero::new(1, 15),
+            Hero::new(1, 23),
+            Hero::new(1, 24),
+            Hero::new(2, 20),
         ],
         chosen_hero: 2,
         boost_damage: 9,
         max_boosts: 7,
+        rules: Rules::default(),
     });
 }
 
@@ -654,70 +329,26 @@ fn poor_positioning() {
     assert_not_solveable(&Problem {
         monster_health: 1000000,
         heroes: vec![
-            Hero {
-                health: 1,
-                damage: 1,
-            },
-            Hero {
-                health: 1,
-                damage: 1,
-            },
-            Hero {
-                health: 1,
-                damage: 1,
-            },
-            Hero {
-                health: 1,
-                damage: 1,
-            },
-            Hero {
-                health: 1,
-                damage: 1,
-            },
-            Hero {
-                health: 1,
-                damage: 1,
-            },
-            Hero {
-                health: 1,
-                damage: 1,
-            },
-            Hero {
-                health: 1,
-                damage: 1,
-            },
-            Hero {
-                health: 1,
-                damage: 1,
-            },
-            Hero {
-                health: 1,
-                damage: 1,
-            },
-            Hero {
-                health: 1,
-                damage: 1,
-            },
-            Hero {
-                health: 1,
-                damage: 1,
-            },
-            Hero {
-                health: 1,
-                damage: 1,
-            },
-            Hero {
-                health: 1,
-                damage: 1,
-            },
-            Hero {
-                health: 1,
-                damage: 1,
-            },
+            Hero::new(1, 1),
+            Hero::new(1, 1),
+            Hero::new(1, 1),
+            Hero::new(1, 1),
+            Hero::new(1, 1),
+            Hero::new(1, 1),
+            Hero::new(1, 1),
+            Hero::new(1, 1),
+            Hero::new(1, 1),
+            Hero::new(1, 1),
+            Hero::new(1, 1),
+            Hero::new(1, 1),
+            Hero::new(1, 1),
+            Hero::new(1, 1),
+            Hero::new(1, 1),
         ],
         chosen_hero: 0,
         boost_damage: 1,
         max_boosts: 10,
+        rules: Rules::default(),
     });
 }
 
@@ -726,133 +357,820 @@ fn the_ultimate() {
     assert_solveable(&Problem {
         monster_health: 9115,
         heroes: vec![
-            Hero {
-                health: 2,
-                damage: 27,
-            },
-            Hero {
-                health: 1,
-                damage: 28,
-            },
-            Hero {
-                health: 1,
-                damage: 14,
-            },
-            Hero {
-                health: 1,
-                damage: 20,
-            },
-            Hero {
-                health: 1,
-                damage: 15,
-            },
-            Hero {
-                health: 2,
-                damage: 29,
-            },
-            Hero {
-                health: 1,
-                damage: 23,
-            },
-            Hero {
-                health: 1,
-                damage: 15,
-            },
-            Hero {
-                health: 1,
-                damage: 8,
-            },
-            Hero {
-                health: 1,
-                damage: 21,
-            },
-            Hero {
-                health: 1,
-                damage: 9,
-            },
-            Hero {
-                health: 1,
-                damage: 17,
-            },
-            Hero {
-                health: 1,
-                damage: 24,
-            },
-            Hero {
-                health: 1,
-                damage: 22,
-            },
-            Hero {
-                health: 1,
-                damage: 23,
-            },
-            Hero {
-                health: 1,
-                damage: 8,
-            },
-            Hero {
-                health: 1,
-                damage: 15,
-            },
-            Hero {
-                health: 1,
-                damage: 20,
-            },
-            Hero {
-                health: 2,
-                damage: 16,
-            },
-            Hero {
-                health: 1,
-                damage: 12,
-            },
-            Hero {
-                health: 2,
-                damage: 15,
-            },
-            Hero {
-                health: 1,
-                damage: 16,
-            },
-            Hero {
-                health: 2,
-                damage: 7,
-            },
-            Hero {
-                health: 1,
-                damage: 22,
-            },
-            Hero {
-                health: 1,
-                damage: 27,
-            },
-            Hero {
-                health: 1,
-                damage: 18,
-            },
-            Hero {
-                health: 1,
-                damage: 18,
-            },
-            Hero {
-                health: 1,
-                damage: 12,
-            },
-            Hero {
-                health: 1,
-                damage: 16,
-            },
-            Hero {
-                health: 2,
-                damage: 26,
-            },
-            Hero {
-                health: 1,
-                damage: 15,
-            },
+            Hero::new(2, 27),
+            Hero::new(1, 28),
+            Hero::new(1, 14),
+            Hero::new(1, 20),
+            Hero::new(1, 15),
+            Hero::new(2, 29),
+            Hero::new(1, 23),
+            Hero::new(1, 15),
+            Hero::new(1, 8),
+            Hero::new(1, 21),
+            Hero::new(1, 9),
+            Hero::new(1, 17),
+            Hero::new(1, 24),
+            Hero::new(1, 22),
+            Hero::new(1, 23),
+            Hero::new(1, 8),
+            Hero::new(1, 15),
+            Hero::new(1, 20),
+            Hero::new(2, 16),
+            Hero::new(1, 12),
+            Hero::new(2, 15),
+            Hero::new(1, 16),
+            Hero::new(2, 7),
+            Hero::new(1, 22),
+            Hero::new(1, 27),
+            Hero::new(1, 18),
+            Hero::new(1, 18),
+            Hero::new(1, 12),
+            Hero::new(1, 16),
+            Hero::new(2, 26),
+            Hero::new(1, 15),
         ],
         chosen_hero: 17,
         boost_damage: 10,
         max_boosts: 10,
+        rules: Rules::default(),
+    });
+}
+
+#[test]
+fn huge_monster() {
+    assert_solveable(&Problem {
+        monster_health: 856867849,
+        heroes: vec![
+            Hero::new(29, 1910),
+            Hero::new(2112, 195),
+            Hero::new(43880, 16),
+            Hero::new(1, 18586),
+        ],
+        chosen_hero: 2,
+        boost_damage: 1,
+        max_boosts: 20,
+        rules: Rules::default(),
+    });
+}
+
+#[test]
+fn huge_monster_attacks_first() {
+    assert_solveable(&Problem {
+        monster_health: 856867849,
+        heroes: vec![
+            Hero::new(29, 1910),
+            Hero::new(2112, 195),
+            Hero::new(43880, 16),
+            Hero::new(1, 18586),
+        ],
+        chosen_hero: 2,
+        boost_damage: 1,
+        max_boosts: 20,
+        rules: Rules {
+            turn_order: TurnOrder::MonsterFirst,
+            ..Rules::default()
+        },
+    });
+}
+
+#[test]
+fn front_hero_dies_before_attacking() {
+    let mut problem = Problem {
+        monster_health: 12,
+        heroes: vec![Hero::new(1, 3), Hero::new(5, 2)],
+        chosen_hero: 0,
+        boost_damage: 10,
+        max_boosts: 2,
+        rules: Rules::default(),
+    };
+    assert_solveable(&problem);
+    problem.rules.turn_order = TurnOrder::MonsterFirst;
+    assert_not_solveable(&problem);
+    problem.chosen_hero = 1;
+    problem.monster_health = 11;
+    problem.max_boosts = 1;
+    problem.boost_damage = 1;
+    assert_solveable(&problem);
+}
+
+#[test]
+fn boost_hero_behind_who_dies_first() {
+    // Hitting everyone kills the last hero after three turns, so their
+    // boosts land early, where the chosen hero's would kill the monster too
+    // soon.
+    assert_solveable(&Problem {
+        monster_health: 214,
+        heroes: vec![
+            Hero::new(10, 8),
+            Hero::new(12, 6),
+            Hero::new(8, 5),
+            Hero::new(2, 4),
+            Hero::new(6, 18),
+        ],
+        chosen_hero: 0,
+        boost_damage: 7,
+        max_boosts: 3,
+        rules: Rules {
+            monster_attack: MonsterAttack {
+                damage: 2,
+                target: Target::All,
+                growth: 0,
+            },
+            ..Rules::default()
+        },
+    });
+}
+
+#[test]
+fn growing_attack() {
+    let mut problem = Problem {
+        monster_health: 60,
+        heroes: vec![Hero::new(10, 5), Hero::new(10, 1)],
+        chosen_hero: 1,
+        boost_damage: 2,
+        max_boosts: 2,
+        rules: Rules::default(),
+    };
+    assert_solveable(&problem);
+    // The front hero now only lasts four turns, and the chosen one six:
+    // too few to wear the monster down.
+    problem.rules.monster_attack.growth = 1;
+    assert_not_solveable(&problem);
+}
+
+#[test]
+fn objectives() {
+    let problem = Problem {
+        monster_health: 36,
+        heroes: vec![Hero::new(1, 5), Hero::new(3, 1), Hero::new(1, 1)],
+        chosen_hero: 1,
+        boost_damage: 6,
+        max_boosts: 4,
+        rules: Rules::default(),
+    };
+    for backend in backends() {
+        let solve = |objective| {
+            let options = SolveOptions {
+                objective,
+                ..SolveOptions::default()
+            };
+            let report = solve_with(&problem, &backend, &options).unwrap();
+            assert_eq!(objective, report.objective);
+            let boosts = report.boosts.unwrap();
+            assert!(solution_is_valid(&problem, &boosts).unwrap());
+            (boosts, report.turn.unwrap())
+        };
+        // A single boost on the chosen hero does it, but only on the last turn.
+        let (boosts, turn) = solve(Objective::FewestBoosts);
+        assert_eq!(1, boosts.len());
+        assert_eq!(3, turn);
+        // Spending more of them gets the kill in much sooner.
+        let (_, turn) = solve(Objective::EarliestKill);
+        assert_eq!(1, turn);
+        let (boosts, _) = solve(Objective::FewestHeroes);
+        assert!(boosts.iter().all(|&hero| hero == boosts[0]));
+    }
+}
+
+#[test]
+fn every_distribution() {
+    let problem = Problem {
+        monster_health: 36,
+        heroes: vec![Hero::new(1, 5), Hero::new(3, 1), Hero::new(1, 1)],
+        chosen_hero: 1,
+        boost_damage: 6,
+        max_boosts: 4,
+        rules: Rules::default(),
+    };
+    let listed: Vec<Vec<usize>> = distributions(&problem)
+        .unwrap()
+        .map(|distribution| distribution.boosts)
+        .collect();
+    for boosts in &listed {
+        assert!(solution_is_valid(&problem, boosts).unwrap());
+    }
+    for backend in backends() {
+        let report = solve_with(&problem, &backend, &SolveOptions::default()).unwrap();
+        assert!(listed.contains(&report.boosts.unwrap()));
+    }
+    assert_eq!(
+        Ok(Count::Exact(listed.len())),
+        count_distributions(&problem, None)
+    );
+    assert_eq!(
+        Ok(Count::AtLeast(3)),
+        count_distributions(&problem, Some(3))
+    );
+}
+
+// What the objective minimises, for comparing solutions that may differ.
+fn objective_value(objective: Objective, report: &SolveReport) -> Option<usize> {
+    let boosts = report.boosts.as_ref()?;
+    let health_boosts = report.health_boosts.clone().unwrap_or_default();
+    Some(match objective {
+        Objective::AnyFeasible => 0,
+        Objective::FewestBoosts => boosts.len() + health_boosts.len(),
+        Objective::EarliestKill => report.turn?,
+        Objective::FewestHeroes => {
+            let mut heroes: Vec<usize> = boosts.iter().chain(&health_boosts).copied().collect();
+            heroes.sort_unstable();
+            heroes.dedup();
+            heroes.len()
+        }
+    })
+}
+
+#[test]
+fn agrees_with_brute_force() {
+    let variants = [
+        (TurnOrder::HeroesFirst, Target::Front, 0),
+        (TurnOrder::MonsterFirst, Target::Front, 0),
+        (TurnOrder::HeroesFirst, Target::All, 1),
+        (TurnOrder::MonsterFirst, Target::All, 0),
+    ];
+    for (seed, &(turn_order, target, growth)) in variants.iter().enumerate() {
+        let config = GeneratorConfig {
+            max_heroes: 4,
+            max_damage: 6,
+            max_boost_damage: 4,
+            max_boosts: 4,
+            rules: Rules {
+                turn_order,
+                monster_attack: MonsterAttack {
+                    damage: 1,
+                    target,
+                    growth,
+                },
+                ..Rules::default()
+            },
+            ..GeneratorConfig::default()
+        };
+        for problem in Generator::new(seed as u64, config).take(30) {
+            for &objective in Objective::all() {
+                let options = SolveOptions {
+                    objective,
+                    ..SolveOptions::default()
+                };
+                let expected = solve_brute_force(&problem, &options).unwrap();
+                for backend in backends() {
+                    let report = solve_with(&problem, &backend, &options).unwrap();
+                    assert_eq!(
+                        objective_value(objective, &expected),
+                        objective_value(objective, &report),
+                        "{} with {} on {:?}",
+                        backend.name(),
+                        objective,
+                        problem
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn speeds_agree_with_brute_force() {
+    let config = GeneratorConfig {
+        max_heroes: 4,
+        max_damage: 6,
+        max_boost_damage: 4,
+        max_boosts: 4,
+        max_speed: 2,
+        ..GeneratorConfig::default()
+    };
+    for problem in Generator::new(12, config).take(60) {
+        for &objective in Objective::all() {
+            let options = SolveOptions {
+                objective,
+                ..SolveOptions::default()
+            };
+            let expected = solve_brute_force(&problem, &options).unwrap();
+            for backend in backends() {
+                let report = solve_with(&problem, &backend, &options).unwrap();
+                assert_eq!(
+                    objective_value(objective, &expected),
+                    objective_value(objective, &report),
+                    "{} with {} on {:?}",
+                    backend.name(),
+                    objective,
+                    problem
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn beyond_i32() {
+    // Far too big for CBC, which only gets numbers up to 2^24 exactly.
+    let problem = Problem {
+        monster_health: 10_000_000_000_000,
+        heroes: vec![
+            Hero::new(3, 2_000_000_000_000),
+            Hero::new(4, 1_000_000_000_000),
+        ],
+        chosen_hero: 1,
+        boost_damage: 500_000_000_000,
+        max_boosts: 3,
+        rules: Rules::default(),
+    };
+    let report = solve_with(&problem, &Backend::Native, &SolveOptions::default()).unwrap();
+    // With a boost the front hero leaves the monster at half a hit on the
+    // third turn.
+    assert_eq!(Some(2), report.turn);
+    assert_eq!(Some(vec![0]), report.boosts);
+    assert!(solution_is_valid(&problem, &[0]).unwrap());
+}
+
+#[test]
+fn overflow_reported() {
+    let problem = Problem {
+        monster_health: i64::MAX,
+        // Together the heroes in front deal more than an i64 holds.
+        heroes: vec![
+            Hero::new(1, i64::MAX / 2 + 1),
+            Hero::new(1, i64::MAX / 2 + 1),
+            Hero::new(1, 1),
+        ],
+        chosen_hero: 2,
+        boost_damage: 0,
+        max_boosts: 0,
+        rules: Rules::default(),
+    };
+    for backend in backends() {
+        match solve_with(&problem, &backend, &SolveOptions::default()) {
+            Err(MonsterError::Overflow(_)) => {}
+            other => panic!("Expected {} to overflow, got {:?}", backend.name(), other),
+        }
+    }
+}
+
+#[test]
+fn explains_overkill() {
+    // The same as unfortunate_overkill.
+    let problem = Problem {
+        monster_health: 200,
+        heroes: vec![
+            Hero::new(2, 1),
+            Hero::new(1, 5),
+            Hero::new(2, 100),
+            Hero::new(1, 1),
+        ],
+        chosen_hero: 3,
+        boost_damage: 5,
+        max_boosts: 3,
+        rules: Rules::default(),
+    };
+    // The third hero leaves 94 health after the first turn, more than the
+    // chosen hero can deal, and kills the monster on the second.
+    assert_eq!(
+        Ok(Some(vec![
+            Infeasibility {
+                reason: Reason::OutOfReach,
+                first_turn: 0,
+                last_turn: Some(0),
+            },
+            Infeasibility {
+                reason: Reason::Overkill,
+                first_turn: 1,
+                last_turn: Some(5),
+            },
+        ])),
+        explain_infeasibility(&problem)
+    );
+}
+
+#[test]
+fn just_enough_boosts() {
+    // The same as woefully_underleveled. The chosen hero only attacks once,
+    // before anyone else, so the boosts need to add 99 damage.
+    let mut problem = Problem {
+        monster_health: 100,
+        heroes: vec![Hero::new(1, 1), Hero::new(1, 2), Hero::new(1, 3)],
+        chosen_hero: 0,
+        boost_damage: 5,
+        max_boosts: 5,
+        rules: Rules::default(),
+    };
+    assert_eq!(Ok(Some(20)), min_max_boosts(&problem));
+    assert_eq!(Ok(Some(20)), min_boost_damage(&problem));
+
+    problem.max_boosts = 20;
+    assert_solveable(&problem);
+    problem.max_boosts = 19;
+    assert_not_solveable(&problem);
+    problem.max_boosts = 5;
+    problem.boost_damage = 20;
+    assert_solveable(&problem);
+    problem.boost_damage = 19;
+    assert_not_solveable(&problem);
+}
+
+#[test]
+fn reachability_agrees_with_brute_force() {
+    let config = GeneratorConfig {
+        max_heroes: 4,
+        max_damage: 6,
+        max_boost_damage: 4,
+        max_boosts: 4,
+        ..GeneratorConfig::default()
+    };
+    let options = SolveOptions {
+        objective: Objective::FewestBoosts,
+        ..SolveOptions::default()
+    };
+    for problem in Generator::new(7, config).take(30) {
+        let map = reachability(&problem, options.objective).unwrap();
+        assert_eq!(problem.heroes.len(), map.heroes.len());
+        for blow in &map.heroes {
+            let mut chosen = problem.clone();
+            chosen.chosen_hero = blow.hero;
+            let expected = solve_brute_force(&chosen, &options).unwrap();
+            assert_eq!(
+                objective_value(options.objective, &expected),
+                blow.boosts.as_ref().map(Vec::len),
+                "hero {} on {:?}",
+                blow.hero,
+                problem
+            );
+            if let Some(ref boosts) = blow.boosts {
+                assert!(solution_is_valid(&chosen, boosts).unwrap());
+            }
+        }
+    }
+}
+
+#[test]
+fn health_boosts_and_caps_agree_with_brute_force() {
+    let variants = [
+        (2, 2, vec![1]),
+        (1, 3, vec![]),
+        (0, 0, vec![0]),
+        (3, 1, vec![2]),
+    ];
+    for (seed, (health_boost, max_health_boosts, boost_caps)) in variants.iter().enumerate() {
+        let config = GeneratorConfig {
+            max_heroes: 4,
+            max_damage: 6,
+            max_boost_damage: 4,
+            max_boosts: 4,
+            rules: Rules {
+                health_boost: *health_boost,
+                max_health_boosts: *max_health_boosts,
+                boost_caps: boost_caps.clone(),
+                ..Rules::default()
+            },
+            ..GeneratorConfig::default()
+        };
+        for problem in Generator::new(seed as u64, config).take(40) {
+            for &objective in Objective::all() {
+                let options = SolveOptions {
+                    objective,
+                    ..SolveOptions::default()
+                };
+                let expected = solve_brute_force(&problem, &options).unwrap();
+                let report = solve_with(&problem, &Backend::Native, &options).unwrap();
+                assert_eq!(
+                    objective_value(objective, &expected).is_some(),
+                    objective_value(objective, &report).is_some(),
+                    "{:?} on {:?}",
+                    objective,
+                    problem
+                );
+                if objective != Objective::AnyFeasible {
+                    assert_eq!(
+                        objective_value(objective, &expected),
+                        objective_value(objective, &report),
+                        "{:?} on {:?}",
+                        objective,
+                        problem
+                    );
+                }
+                if let Some(ref boosts) = report.boosts {
+                    let health_boosts = report.health_boosts.clone().unwrap_or_default();
+                    assert!(
+                        solution_is_valid_with_health(&problem, boosts, &health_boosts).unwrap()
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn health_boosts_are_unsupported_elsewhere() {
+    let mut problem = Problem {
+        monster_health: 5,
+        heroes: vec![Hero::new(1, 2)],
+        chosen_hero: 0,
+        boost_damage: 1,
+        max_boosts: 1,
+        rules: Rules::default(),
+    };
+    problem.rules.health_boost = 2;
+    problem.rules.max_health_boosts = 1;
+    let unsupported = |result: Result<(), MonsterError>| match result {
+        Err(MonsterError::Unsupported(_)) => {}
+        other => panic!("Expected the problem to be unsupported, got {:?}", other),
+    };
+    unsupported(solve_with(&problem, &Backend::Cbc, &SolveOptions::default()).map(|_| ()));
+    unsupported(solve_schedule(&problem).map(|_| ()));
+    unsupported(min_max_boosts(&problem).map(|_| ()));
+    unsupported(distributions(&problem).map(|_| ()));
+    unsupported(reachability(&problem, Objective::AnyFeasible).map(|_| ()));
+    unsupported(explain_infeasibility(&problem).map(|_| ()));
+}
+
+#[test]
+fn random_damage_needs_monte_carlo() {
+    let mut problem = Problem {
+        monster_health: 5,
+        heroes: vec![Hero {
+            max_damage: Some(3),
+            ..Hero::new(1, 2)
+        }],
+        chosen_hero: 0,
+        boost_damage: 3,
+        max_boosts: 1,
+        rules: Rules::default(),
+    };
+    let unsupported = |result: Result<(), MonsterError>| match result {
+        Err(MonsterError::Unsupported(_)) => {}
+        other => panic!("Expected the problem to be unsupported, got {:?}", other),
+    };
+    for backend in &[Backend::Native, Backend::BruteForce, Backend::Cbc] {
+        unsupported(solve_with(&problem, backend, &SolveOptions::default()).map(|_| ()));
+    }
+    unsupported(solution_is_valid(&problem, &[]).map(|_| ()));
+    unsupported(solve_schedule(&problem).map(|_| ()));
+    unsupported(min_max_boosts(&problem).map(|_| ()));
+    unsupported(distributions(&problem).map(|_| ()));
+    unsupported(reachability(&problem, Objective::AnyFeasible).map(|_| ()));
+    unsupported(explain_infeasibility(&problem).map(|_| ()));
+    problem.heroes[0].max_damage = None;
+    let estimate = estimate_win_probability(&problem, &[0], 10, 0).unwrap();
+    assert_eq!(10, estimate.wins);
+}
+
+#[test]
+fn monte_carlo_without_ranges_matches_the_simulation() {
+    // With every range a single value, each trial plays out the same fight.
+    let problems = Generator::new(12, GeneratorConfig::default()).take(200);
+    for problem in problems {
+        let mut ranged = problem.clone();
+        for hero in &mut ranged.heroes {
+            hero.max_damage = Some(hero.damage);
+        }
+        let boosts = vec![problem.chosen_hero; problem.max_boosts.min(3)];
+        let estimate = estimate_win_probability(&ranged, &boosts, 2, 0).unwrap();
+        let wins = if solution_is_valid(&problem, &boosts).unwrap() {
+            2
+        } else {
+            0
+        };
+        assert_eq!(wins, estimate.wins, "{:?}", problem);
+    }
+}
+
+#[test]
+fn limited_runs_keep_what_works() {
+    let mut backends = vec![Backend::Native, Backend::BruteForce];
+    if CbcBackend::is_available() {
+        backends.push(Backend::Cbc);
+    }
+    let config = GeneratorConfig {
+        max_heroes: 5,
+        max_boosts: 5,
+        ..GeneratorConfig::default()
+    };
+    for problem in Generator::new(13, config).take(100) {
+        let options = SolveOptions {
+            objective: Objective::FewestBoosts,
+            ..SolveOptions::default()
+        };
+        let fewest = solve_with(&problem, &Backend::Native, &options)
+            .unwrap()
+            .boosts
+            .map(|boosts| boosts.len());
+        for backend in &backends {
+            for &node_limit in &[0, 1, 5, 1000] {
+                let options = SolveOptions {
+                    node_limit: Some(node_limit),
+                    ..options.clone()
+                };
+                let report = solve_with(&problem, backend, &options).unwrap();
+                match report.status {
+                    SolveStatus::Solved => {
+                        assert_eq!(fewest, report.boosts.as_ref().map(Vec::len))
+                    }
+                    SolveStatus::Infeasible => assert_eq!(None, fewest),
+                    SolveStatus::Unknown => {}
+                    SolveStatus::TimedOut => panic!("{:?} timed out without a time limit", backend),
+                }
+                if let Some(ref boosts) = report.boosts {
+                    assert!(solution_is_valid(&problem, boosts).unwrap());
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn batch_matches_sequential() {
+    let problems: Vec<Problem> = Generator::new(11, GeneratorConfig::default())
+        .take(50)
+        .collect();
+    let items = problems.iter().enumerate().map(|(i, problem)| BatchItem {
+        name: i.to_string(),
+        problem: Ok(problem.clone()),
     });
+    let mut results = Vec::new();
+    solve_batch(
+        items,
+        &Backend::Native,
+        &SolveOptions::default(),
+        4,
+        |result| results.push(result),
+    );
+    assert_eq!(problems.len(), results.len());
+    for (i, (problem, result)) in problems.iter().zip(&results).enumerate() {
+        let report = solve_with(problem, &Backend::Native, &SolveOptions::default()).unwrap();
+        assert_eq!(i.to_string(), result.name);
+        assert_eq!(report.status.to_string(), result.status);
+        assert_eq!(report.boosts, result.boosts);
+        assert_eq!(report.boosts.as_ref().map(|_| true), result.valid);
+    }
+}
+
+#[test]
+fn schedules_beat_upfront_boosts() {
+    // Boosting everyone on the first turn is the same as boosting them before
+    // combat, so a schedule can always do at least as well.
+    let options = SolveOptions {
+        objective: Objective::EarliestKill,
+        ..SolveOptions::default()
+    };
+    for problem in Generator::new(13, GeneratorConfig::default()).take(200) {
+        let solution = solve_schedule(&problem).unwrap();
+        if let Some(ref solution) = solution {
+            assert!(schedule_is_valid(&problem, &solution.schedule).unwrap());
+        }
+        let report = solve_with(&problem, &Backend::Native, &options).unwrap();
+        if let Some(turn) = report.turn {
+            let scheduled = solution.map(|solution| solution.turn);
+            assert!(
+                scheduled.is_some_and(|scheduled| scheduled <= turn),
+                "{:?}",
+                problem
+            );
+        }
+    }
+}
+
+#[test]
+fn schedules_agree_with_exhaustive_search() {
+    let config = GeneratorConfig {
+        max_heroes: 3,
+        max_damage: 3,
+        max_boost_damage: 4,
+        max_boosts: 3,
+        max_boosts_per_turn: 2,
+        ..GeneratorConfig::default()
+    };
+    let mut checked = 0;
+    for problem in Generator::new(17, config).take(300) {
+        // No boost is worth giving after the fight would end without any.
+        let last_turn = simulate(&problem, &[]).unwrap().turn;
+        if last_turn > 6 {
+            continue;
+        }
+        let pairs: Vec<(usize, usize)> = (0..=last_turn)
+            .flat_map(|turn| (0..problem.heroes.len()).map(move |hero| (turn, hero)))
+            .collect();
+        let best = boost_lists(pairs.len(), problem.max_boosts)
+            .into_iter()
+            .map(|list| list.iter().map(|&i| pairs[i]).collect::<Vec<_>>())
+            .filter(|schedule| matches!(schedule_is_valid(&problem, schedule), Ok(true)))
+            .map(|schedule| {
+                let outcome = simulate_schedule(&problem, &schedule).unwrap();
+                (outcome.turn, schedule.len())
+            })
+            .min();
+        let solution = solve_schedule(&problem).unwrap();
+        assert_eq!(
+            best,
+            solution.map(|solution| (solution.turn, solution.schedule.len())),
+            "{:?}",
+            problem
+        );
+        checked += 1;
+    }
+    assert!(checked > 100, "{}", checked);
+}
+
+// Every boost list for a fight, sorted, with at most `max` boosts.
+fn boost_lists(heroes: usize, max: usize) -> Vec<Vec<usize>> {
+    let mut lists = vec![vec![]];
+    let mut last = vec![vec![]];
+    for _ in 0..max {
+        last = last
+            .iter()
+            .flat_map(|list: &Vec<usize>| {
+                (list.last().copied().unwrap_or(0)..heroes).map(move |hero| {
+                    let mut list = list.clone();
+                    list.push(hero);
+                    list
+                })
+            })
+            .collect();
+        lists.extend(last.iter().cloned());
+    }
+    lists
+}
+
+#[test]
+fn campaigns_agree_with_brute_force() {
+    let config = GeneratorConfig {
+        max_heroes: 3,
+        max_damage: 4,
+        max_boost_damage: 3,
+        max_health: 8,
+        max_boosts: 2,
+        ..GeneratorConfig::default()
+    };
+    let mut problems = Generator::new(7, config);
+    for case in 0..200 {
+        let first = problems.next().unwrap();
+        let heroes = first.heroes.len();
+        let fights = (0..3)
+            .map(|i| {
+                let problem = problems.next().unwrap();
+                Fight {
+                    monster_health: problem.monster_health % 6 + 1,
+                    chosen_hero: (first.chosen_hero + i) % heroes,
+                }
+            })
+            .collect();
+        let campaign = Campaign {
+            heroes: first.heroes.clone(),
+            fights,
+            boost_damage: first.boost_damage,
+            budget: if case % 2 == 0 {
+                BoostBudget::Shared(first.max_boosts + 1)
+            } else {
+                BoostBudget::PerFight(vec![first.max_boosts; 3])
+            },
+            rules: Rules {
+                turn_order: if case % 4 < 2 {
+                    TurnOrder::HeroesFirst
+                } else {
+                    TurnOrder::MonsterFirst
+                },
+                monster_attack: MonsterAttack {
+                    damage: 1,
+                    target: if case % 3 == 0 {
+                        Target::All
+                    } else {
+                        Target::Front
+                    },
+                    growth: case % 5 / 4,
+                },
+                ..Rules::default()
+            },
+        };
+
+        let budget = match campaign.budget {
+            BoostBudget::Shared(boosts) => boosts,
+            BoostBudget::PerFight(ref budgets) => budgets[0],
+        };
+        let lists = boost_lists(heroes, budget);
+        let mut expected = None;
+        for a in &lists {
+            for b in &lists {
+                for c in &lists {
+                    let plan = vec![a.clone(), b.clone(), c.clone()];
+                    let total = a.len() + b.len() + c.len();
+                    if expected.is_some_and(|best| total >= best) {
+                        continue;
+                    }
+                    if let Ok(outcome) = simulate_campaign(&campaign, &plan) {
+                        if outcome.won {
+                            expected = Some(total);
+                        }
+                    }
+                }
+            }
+        }
+
+        let plan = solve_campaign(&campaign).unwrap();
+        assert_eq!(
+            expected,
+            plan.as_ref().map(CampaignPlan::total_boosts),
+            "{:?}",
+            campaign
+        );
+        if let Some(plan) = plan {
+            assert!(simulate_campaign(&campaign, &plan.boosts()).unwrap().won);
+        }
+    }
 }
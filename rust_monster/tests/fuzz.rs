@@ -0,0 +1,66 @@
+extern crate rust_monster;
+use rust_monster::backend::{Backend, CbcBackend, MonsterSolver};
+use rust_monster::fuzz::fuzz;
+use rust_monster::generate::GeneratorConfig;
+use rust_monster::problem::{MonsterAttack, Rules, Target, TurnOrder};
+
+// Runs generated problems through the backend under every variation of the
+// rules, and fails with the shrunk problems it gets wrong, written as tests
+// for examples.rs.
+fn assert_agrees(backend: Backend, count: usize) {
+    let variants = [
+        (TurnOrder::HeroesFirst, Target::Front, 0),
+        (TurnOrder::MonsterFirst, Target::Front, 0),
+        (TurnOrder::HeroesFirst, Target::All, 0),
+        (TurnOrder::MonsterFirst, Target::All, 1),
+        (TurnOrder::HeroesFirst, Target::Front, 2),
+    ];
+    let mut tests = Vec::new();
+    for (seed, &(turn_order, target, growth)) in variants.iter().enumerate() {
+        let config = GeneratorConfig {
+            rules: Rules {
+                turn_order,
+                monster_attack: MonsterAttack {
+                    damage: 1,
+                    target,
+                    growth,
+                },
+                ..Rules::default()
+            },
+            ..GeneratorConfig::default()
+        };
+        for failure in fuzz(&backend, seed as u64, count, config).unwrap() {
+            let name = format!(
+                "fuzz_{}_seed_{}_case_{}",
+                backend.name(),
+                seed,
+                failure.case
+            );
+            tests.push(format!(
+                "// {}\n{}",
+                failure.disagreement,
+                failure.example_test(&name)
+            ));
+        }
+    }
+    if !tests.is_empty() {
+        panic!(
+            "{} disagrees with the simulation:\n\n{}",
+            backend.name(),
+            tests.join("\n")
+        );
+    }
+}
+
+#[test]
+fn fuzz_native() {
+    assert_agrees(Backend::Native, 1000);
+}
+
+#[test]
+fn fuzz_cbc() {
+    // Every problem starts a cbc process, so we check fewer.
+    if CbcBackend::is_available() {
+        assert_agrees(Backend::Cbc, 40);
+    }
+}